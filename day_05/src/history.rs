@@ -0,0 +1,239 @@
+use std::{
+    fmt,
+    io::{self, BufRead, Write},
+};
+
+use crate::{draw_stacks, move_crates, Move, Stacks};
+
+// what a single instruction did, so that it can be shown and undone
+#[derive(Debug, PartialEq)]
+pub struct Step {
+    pub index: usize,
+    pub crates: Vec<char>,
+    pub src: usize,
+    pub dest: usize,
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let crates: String = self.crates.iter().collect();
+        write!(
+            f,
+            "#{}: moved {} from {} to {}",
+            self.index + 1,
+            crates,
+            self.src,
+            self.dest
+        )
+    }
+}
+
+// steps through a list of moves, keeping track of where we are so they can be undone again
+pub struct History {
+    stacks: Stacks,
+    moves: Vec<Move>,
+    position: usize, // number of moves that have been applied
+}
+
+impl History {
+    pub fn new(stacks: Stacks, moves: Vec<Move>) -> Self {
+        History {
+            stacks,
+            moves,
+            position: 0,
+        }
+    }
+
+    pub fn stacks(&self) -> &Stacks {
+        &self.stacks
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn num_moves(&self) -> usize {
+        self.moves.len()
+    }
+
+    pub fn step_forward(&mut self) -> Option<Step> {
+        let m = *self.moves.get(self.position)?;
+        let crates = move_crates(&mut self.stacks, m.num, m.src, m.dest);
+        self.position += 1;
+        Some(Step {
+            index: self.position - 1,
+            crates,
+            src: m.src,
+            dest: m.dest,
+        })
+    }
+
+    // the crane lifts crates as a block, so moving them straight back puts everything where it was
+    pub fn step_back(&mut self) -> Option<Step> {
+        self.position = self.position.checked_sub(1)?;
+        let m = self.moves[self.position];
+        let crates = move_crates(&mut self.stacks, m.num, m.dest, m.src);
+        Some(Step {
+            index: self.position,
+            crates,
+            src: m.dest,
+            dest: m.src,
+        })
+    }
+
+    // leaves the stacks as they are after the first [position] moves
+    pub fn jump_to(&mut self, position: usize) -> Vec<Step> {
+        let position = position.min(self.moves.len());
+        let mut steps = Vec::new();
+        while self.position < position {
+            steps.extend(self.step_forward());
+        }
+        while self.position > position {
+            steps.extend(self.step_back());
+        }
+        steps
+    }
+}
+
+const HELP: &str = "commands:
+  n [count]  step forward (default 1)
+  b [count]  step back (default 1)
+  j <k>      jump to just after instruction k (0 is the initial state)
+  s          show the stacks
+  q          quit";
+
+fn show(history: &History, output: &mut impl Write) -> io::Result<()> {
+    writeln!(
+        output,
+        "{}\n({} of {} moves done)",
+        draw_stacks(history.stacks()),
+        history.position(),
+        history.num_moves()
+    )
+}
+
+// a little command prompt for stepping through the moves, reads commands from [input] until it runs out or gets a q
+pub fn interactive(
+    history: &mut History,
+    input: impl BufRead,
+    mut output: impl Write,
+) -> io::Result<()> {
+    writeln!(output, "{}", HELP)?;
+    show(history, &mut output)?;
+    for line in input.lines() {
+        let line = line?;
+        let mut words = line.split_whitespace();
+        let command = words.next().unwrap_or("n");
+        let arg: Option<usize> = words.next().and_then(|word| word.parse().ok());
+        let steps = match command {
+            "n" | "next" => {
+                let target = history.position() + arg.unwrap_or(1);
+                history.jump_to(target)
+            }
+            "b" | "back" => {
+                let target = history.position().saturating_sub(arg.unwrap_or(1));
+                history.jump_to(target)
+            }
+            "j" | "jump" => match arg {
+                Some(k) => history.jump_to(k),
+                None => {
+                    writeln!(output, "jump needs an instruction number")?;
+                    continue;
+                }
+            },
+            "s" | "show" => Vec::new(),
+            "q" | "quit" => break,
+            _ => {
+                writeln!(output, "{}", HELP)?;
+                continue;
+            }
+        };
+        for step in steps {
+            writeln!(output, "{}", step)?;
+        }
+        show(history, &mut output)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_initial_state, parse_instructions};
+
+    fn sample_history() -> History {
+        let stacks = parse_initial_state(
+            "    [D]
+[N] [C]
+[Z] [M] [P]
+ 1   2   3 ",
+        );
+        let moves = parse_instructions(
+            "move 1 from 2 to 1
+move 3 from 1 to 3
+move 2 from 2 to 1
+move 1 from 1 to 2",
+        );
+        History::new(stacks, moves)
+    }
+
+    #[test]
+    fn test_step_forward_and_back() {
+        let mut history = sample_history();
+        let initial = history.stacks().clone();
+        assert_eq!(
+            history.step_forward(),
+            Some(Step {
+                index: 0,
+                crates: vec!['D'],
+                src: 2,
+                dest: 1
+            })
+        );
+        assert_eq!(
+            history.step_forward(),
+            Some(Step {
+                index: 1,
+                crates: vec!['Z', 'N', 'D'],
+                src: 1,
+                dest: 3
+            })
+        );
+        assert_eq!(
+            history.step_back(),
+            Some(Step {
+                index: 1,
+                crates: vec!['Z', 'N', 'D'],
+                src: 3,
+                dest: 1
+            })
+        );
+        history.step_back();
+        assert_eq!(history.step_back(), None);
+        assert_eq!(history.stacks(), &initial);
+    }
+
+    #[test]
+    fn test_jump_to() {
+        let mut history = sample_history();
+        assert_eq!(history.jump_to(4).len(), 4);
+        assert_eq!(crate::get_tops(history.stacks()), "MCD");
+        assert_eq!(history.jump_to(10).len(), 0);
+        let steps = history.jump_to(1);
+        assert_eq!(steps.len(), 3);
+        assert_eq!(steps[0].to_string(), "#4: moved C from 2 to 1");
+        assert_eq!(crate::get_tops(history.stacks()), "DCP");
+    }
+
+    #[test]
+    fn test_interactive() {
+        let mut history = sample_history();
+        let mut output = Vec::new();
+        interactive(&mut history, "n 2\nb\nj 4\nq\nn\n".as_bytes(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("#1: moved D from 2 to 1\n#2: moved ZND from 1 to 3\n"));
+        assert!(output.contains("#2: moved ZND from 3 to 1\n"));
+        assert!(output.ends_with("(4 of 4 moves done)\n"));
+        assert_eq!(history.position(), 4);
+    }
+}
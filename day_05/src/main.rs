@@ -1,34 +1,92 @@
-use std::collections::HashMap;
+mod history;
+
+use std::collections::BTreeMap;
 
 type Stack = Vec<char>;
-type Stacks = HashMap<usize, Stack>;
+type Stacks = BTreeMap<usize, Stack>;
+
+// a stack label from the bottom row of the drawing, along with the columns it spans
+struct Label {
+    id: usize,
+    start: usize,
+    end: usize,
+}
 
-// note: this assumes that stacks numbers can be infered from their position
+impl Label {
+    // how far a crate's letter is from this label, 0 if it's directly underneath
+    fn distance(&self, col: usize) -> usize {
+        if col < self.start {
+            self.start - col
+        } else if col >= self.end {
+            col - self.end + 1
+        } else {
+            0
+        }
+    }
+}
+
+fn parse_labels(line: &str) -> Vec<Label> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut labels = Vec::new();
+    let mut col = 0;
+    while col < chars.len() {
+        if chars[col].is_ascii_digit() {
+            let start = col;
+            while col < chars.len() && chars[col].is_ascii_digit() {
+                col += 1;
+            }
+            let id = chars[start..col]
+                .iter()
+                .collect::<String>()
+                .parse()
+                .expect("unparseable stack id");
+            labels.push(Label {
+                id,
+                start,
+                end: col,
+            });
+        } else {
+            col += 1;
+        }
+    }
+    labels
+}
+
+fn is_label_line(line: &str) -> bool {
+    let mut words = line.split_whitespace().peekable();
+    words.peek().is_some() && words.all(|word| word.parse::<usize>().is_ok())
+}
+
+// stack ids are read from the label row, and each crate goes to whichever label it sits closest to,
+// so multi-digit ids, gaps in the numbering and lines with trailing spaces trimmed off all work
 fn parse_initial_state(input: &str) -> Stacks {
-    let (mut stack_lines, id_line): (Vec<&str>, Vec<&str>) =
-        input.lines().partition(|l| !l.starts_with(" 1"));
-    let num_stacks: usize = id_line.get(0).unwrap().split_whitespace().count();
-    let mut stacks: Stacks = HashMap::with_capacity(num_stacks);
-    stack_lines.reverse();
-    for line in stack_lines {
+    let lines: Vec<&str> = input
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+    let (id_line, stack_lines) = lines
+        .split_last()
+        .filter(|(id_line, _)| is_label_line(id_line))
+        .expect("drawing must end with a row of stack ids");
+    let labels = parse_labels(id_line);
+    let mut stacks: Stacks = labels.iter().map(|l| (l.id, Vec::new())).collect();
+    for line in stack_lines.iter().rev() {
         let chars: Vec<char> = line.chars().collect();
-        for stack_num in 1..=num_stacks {
-            let col = (stack_num - 1) * 4 + 1;
-            let char = chars.get(col).unwrap();
-            if char.eq(&' ') {
+        for (col, window) in chars.windows(3).enumerate() {
+            if window[0] != '[' || window[2] != ']' {
                 continue;
             }
-            if !stacks.contains_key(&stack_num) {
-                stacks.insert(stack_num, Vec::new());
-            }
-            let stack = stacks.get_mut(&stack_num).unwrap();
-            stack.push(char.clone());
+            let label = labels
+                .iter()
+                .min_by_key(|label| label.distance(col + 1))
+                .expect("there must be at least one stack");
+            stacks.entry(label.id).or_default().push(window[1]);
         }
     }
     stacks
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 struct Move {
     num: usize,
     src: usize,
@@ -48,22 +106,72 @@ fn parse_instructions(input: &str) -> Vec<Move> {
     moves
 }
 
+// moves [num] crates from the top of one stack to another in a single lift (keeping their order),
+// and returns the crates that were moved
+fn move_crates(stacks: &mut Stacks, num: usize, src: usize, dest: usize) -> Vec<char> {
+    let src_stack = stacks
+        .get_mut(&src)
+        .unwrap_or_else(|| panic!("no stack with id {}", src));
+    if src_stack.len() < num {
+        panic!(
+            "can't move {} crates from stack {}, it only has {}",
+            num,
+            src,
+            src_stack.len()
+        );
+    }
+    let things = src_stack.split_off(src_stack.len() - num);
+    stacks
+        .get_mut(&dest)
+        .unwrap_or_else(|| panic!("no stack with id {}", dest))
+        .extend_from_slice(&things);
+    things
+}
+
 fn do_moves(mut stacks: Stacks, moves: Vec<Move>) -> Stacks {
     for instruction in moves {
-        let mut src = stacks.get_mut(&instruction.src).unwrap();
-        let mut things = src.split_off(src.len() - instruction.num);
-        stacks.get_mut(&instruction.dest).unwrap().append(&mut things);
+        move_crates(
+            &mut stacks,
+            instruction.num,
+            instruction.src,
+            instruction.dest,
+        );
         println!("\nmove:{:?}\n stacks now: {:?}\n", instruction, stacks);
     }
     stacks
 }
 
-fn get_tops(stacks: &Stacks) -> Vec<char> {
-    let mut tops: Vec<char> = Vec::new();
-    for key in 1..=stacks.len() {
-        tops.push(stacks.get(&key).unwrap().last().unwrap().clone())
+// draws the stacks the same way the puzzle input does, with the ids along the bottom
+fn draw_stacks(stacks: &Stacks) -> String {
+    let widths: Vec<usize> = stacks
+        .keys()
+        .map(|id| id.to_string().len().max(3))
+        .collect();
+    let height = stacks.values().map(|stack| stack.len()).max().unwrap_or(0);
+    let mut lines: Vec<String> = Vec::with_capacity(height + 1);
+    for level in (0..height).rev() {
+        let cells: Vec<String> = stacks
+            .values()
+            .zip(&widths)
+            .map(|(stack, width)| match stack.get(level) {
+                Some(c) => format!("{:^width$}", format!("[{}]", c), width = width),
+                None => " ".repeat(*width),
+            })
+            .collect();
+        lines.push(cells.join(" "));
     }
-    tops
+    let ids: Vec<String> = stacks
+        .keys()
+        .zip(&widths)
+        .map(|(id, width)| format!("{:^width$}", id, width = width))
+        .collect();
+    lines.push(ids.join(" "));
+    lines.join("\n")
+}
+
+// the top crate of each stack, in id order, skipping any stacks that are empty
+fn get_tops(stacks: &Stacks) -> String {
+    stacks.values().filter_map(|stack| stack.last()).collect()
 }
 
 fn main() {
@@ -76,7 +184,107 @@ fn main() {
         "instructions_input:\n{}\n{:?}",
         instructions_input, instructions
     );
+    if std::env::args().nth(1).as_deref() == Some("step") {
+        let mut history = history::History::new(stacks, instructions);
+        let stdin = std::io::stdin();
+        history::interactive(&mut history, stdin.lock(), std::io::stdout())
+            .expect("failed to talk to the terminal");
+        return;
+    }
     stacks = do_moves(stacks, instructions);
     println!("after moves:\n{:?}", stacks);
-    println!("top letters: {:?}", get_tops(&stacks)); // todo: figure out why join doesn't work here
+    println!("top letters: {}", get_tops(&stacks));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_DRAWING: &str = "    [D]    
+[N] [C]    
+[Z] [M] [P]
+ 1   2   3 ";
+
+    #[test]
+    fn test_parse_sample() {
+        assert_eq!(
+            parse_initial_state(SAMPLE_DRAWING),
+            BTreeMap::from([
+                (1, vec!['Z', 'N']),
+                (2, vec!['M', 'C', 'D']),
+                (3, vec!['P']),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_trimmed_lines() {
+        let trimmed = SAMPLE_DRAWING
+            .lines()
+            .map(|line| line.trim_end())
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert_eq!(
+            parse_initial_state(&trimmed),
+            parse_initial_state(SAMPLE_DRAWING)
+        );
+    }
+
+    #[test]
+    fn test_parse_multi_digit_ids() {
+        let input = "[A]                                     [K]
+[B] [C] [D] [E] [F] [G] [H] [I]     [J] [L]
+ 1   2   3   4   5   6   7   8   9  10  11";
+        let stacks = parse_initial_state(input);
+        assert_eq!(stacks.len(), 11);
+        assert_eq!(stacks[&9], vec![]);
+        assert_eq!(stacks[&10], vec!['J']);
+        assert_eq!(stacks[&11], vec!['L', 'K']);
+    }
+
+    #[test]
+    fn test_parse_non_contiguous_ids() {
+        let input = "[A]
+[B]     [C]
+ 3   7   12";
+        assert_eq!(
+            parse_initial_state(input),
+            BTreeMap::from([(3, vec!['B', 'A']), (7, vec![]), (12, vec!['C'])])
+        );
+    }
+
+    #[test]
+    fn test_get_tops() {
+        let stacks = BTreeMap::from([(2, vec!['A', 'B']), (5, vec![]), (10, vec!['C'])]);
+        assert_eq!(get_tops(&stacks), "BC");
+    }
+
+    #[test]
+    fn test_draw_round_trip() {
+        let stacks = BTreeMap::from([(1, vec!['A']), (4, vec![]), (12, vec!['B', 'C'])]);
+        assert_eq!(parse_initial_state(&draw_stacks(&stacks)), stacks);
+        assert_eq!(
+            draw_stacks(&parse_initial_state(SAMPLE_DRAWING)),
+            SAMPLE_DRAWING
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "only has 1")]
+    fn test_move_too_many() {
+        let mut stacks = parse_initial_state(SAMPLE_DRAWING);
+        move_crates(&mut stacks, 2, 3, 1);
+    }
+
+    #[test]
+    fn test_sample_moves() {
+        let stacks = parse_initial_state(SAMPLE_DRAWING);
+        let moves = parse_instructions(
+            "move 1 from 2 to 1
+move 3 from 1 to 3
+move 2 from 2 to 1
+move 1 from 1 to 2",
+        );
+        assert_eq!(get_tops(&do_moves(stacks, moves)), "MCD"); // crates move together, so this is the part 2 answer
+    }
 }
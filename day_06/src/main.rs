@@ -1,6 +1,26 @@
-use std::collections::{HashSet, VecDeque};
+mod segment;
+mod stream;
+
+use std::{
+    collections::{HashSet, VecDeque},
+    time::Instant,
+};
 
 fn main() {
+    if std::env::args().nth(1).as_deref() == Some("bench") {
+        bench();
+        return;
+    }
+    if std::env::args().nth(1).as_deref() == Some("stdin") {
+        // e.g. `nc example.com 1234 | cargo run -- stdin`, markers are printed as soon as they arrive
+        stream::read_markers(
+            std::io::stdin().lock(),
+            &[PACKET_MARKER_SIZE, MESSAGE_MARKER_SIZE],
+            |size, position| println!("marker of size {} at {}", size, position),
+        )
+        .expect("failed to read stdin");
+        return;
+    }
     let input = include_str!("input.txt");
     println!(
         "start of packet marker: {:?}",
@@ -10,18 +30,43 @@ fn main() {
         "start of message marker: {:?}",
         find_start_of_message_marker(input)
     );
+    println!(
+        "number of start of packet markers: {}",
+        segment::find_all_markers(input, PACKET_MARKER_SIZE).count()
+    );
+    for message in segment::segment(input.trim_end(), MESSAGE_MARKER_SIZE) {
+        println!(
+            "message at {:?}: {:?}",
+            message.body,
+            String::from_utf8_lossy(message.content)
+        );
+    }
 }
 
+const PACKET_MARKER_SIZE: usize = 4;
+const MESSAGE_MARKER_SIZE: usize = 14;
+
 fn find_start_of_packet_marker(input: &str) -> Option<usize> {
-    find_marker(input, 4)
+    find_marker(input, PACKET_MARKER_SIZE)
 }
 
 fn find_start_of_message_marker(input: &str) -> Option<usize> {
-    find_marker(input, 14)
+    find_marker(input, MESSAGE_MARKER_SIZE)
 }
 
 // finds the first substring of [size] unique characters and returns the position of the final character in that substring
+// works on bytes, so the input is expected to be ascii (which the puzzle input is)
 fn find_marker(input: &str, size: usize) -> Option<usize> {
+    find_marker_bytes(input.as_bytes(), size)
+}
+
+// a single pass with no allocations, regardless of the window size
+fn find_marker_bytes(input: &[u8], size: usize) -> Option<usize> {
+    stream::MarkerDetector::new(size).feed(input)
+}
+
+// the original version, kept around to check the fast one against
+fn find_marker_naive(input: &str, size: usize) -> Option<usize> {
     let mut window: VecDeque<char> = VecDeque::with_capacity(size);
     let char_indices = input.char_indices();
     for (index, char) in char_indices {
@@ -39,6 +84,56 @@ fn find_marker(input: &str, size: usize) -> Option<usize> {
     None
 }
 
+// xorshift, just enough randomness to generate test streams without pulling in a crate
+fn next_random(state: &mut u64) -> u64 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    *state
+}
+
+// a random stream of [len] bytes that only uses [size] - 1 different letters, so there's no marker
+// until the very end where one is tacked on. [size] needs to be at least 3 for that to hold
+fn generate_stream(len: usize, size: usize, seed: u64) -> String {
+    let alphabet: Vec<char> = (b'!'..=b'~').map(|b| b as char).collect();
+    let (new_letter, letters) = alphabet[..size.min(alphabet.len())]
+        .split_last()
+        .expect("size must be at least 1");
+    let mut state = seed.max(1);
+    let mut stream: String = (0..len.saturating_sub(size + 1).max(1))
+        .map(|_| letters[(next_random(&mut state) % letters.len() as u64) as usize])
+        .collect();
+    // repeat the last letter so no window can reach back past it, then finish with every letter at once
+    let last = stream.chars().last().unwrap();
+    stream.push(last);
+    stream.push(*new_letter);
+    stream.push(last);
+    stream.extend(letters.iter().filter(|&&c| c != last));
+    stream
+}
+
+fn bench() {
+    let len = 4_000_000;
+    for size in [4, 14, 50] {
+        let stream = generate_stream(len, size, 2022);
+        let naive_time = Instant::now();
+        let naive = find_marker_naive(&stream, size);
+        let naive_time = naive_time.elapsed();
+        let fast_time = Instant::now();
+        let fast = find_marker(&stream, size);
+        let fast_time = fast_time.elapsed();
+        assert_eq!(naive, fast);
+        println!(
+            "{} byte stream, window {}: naive {:?}, fast {:?} (marker at {:?})",
+            stream.len(),
+            size,
+            naive_time,
+            fast_time,
+            fast
+        );
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -116,4 +211,54 @@ mod tests {
             Some(26)
         );
     }
+
+    #[test]
+    fn test_large_windows() {
+        let alphabet: String = (0..=255u8)
+            .map(|b| b as char)
+            .filter(char::is_ascii)
+            .collect();
+        let input = format!("aa{}", alphabet);
+        assert_eq!(find_marker(&input, 128), Some(130));
+        assert_eq!(find_marker(&input, 129), None);
+        assert_eq!(
+            find_marker_bytes(&(0..=255u8).collect::<Vec<_>>(), 256),
+            Some(256)
+        );
+        assert_eq!(
+            find_marker_bytes(&(0..=255u8).collect::<Vec<_>>(), 257),
+            None
+        );
+    }
+
+    #[test]
+    fn test_against_naive() {
+        for seed in 1..50 {
+            let mut state = seed;
+            let len = (next_random(&mut state) % 200) as usize;
+            // small alphabets so that markers are rare enough to be interesting
+            let letters = 2 + (next_random(&mut state) % 10) as u8;
+            let input: String = (0..len)
+                .map(|_| (b'a' + (next_random(&mut state) % letters as u64) as u8) as char)
+                .collect();
+            for size in 0..=12 {
+                assert_eq!(
+                    find_marker(&input, size),
+                    find_marker_naive(&input, size),
+                    "input {} size {}",
+                    input,
+                    size
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_generate_stream() {
+        for size in 3..=50 {
+            let stream = generate_stream(1000, size, size as u64);
+            assert_eq!(stream.len(), 1000);
+            assert_eq!(find_marker(&stream, size), Some(1000), "size {}", size);
+        }
+    }
 }
@@ -0,0 +1,174 @@
+use std::ops::Range;
+
+use crate::stream::UniqueRun;
+
+// every position where a run of [size] unique bytes ends, not just the first one.
+// positions are counted from 1 like the puzzle does, so each one is also the index of the byte after the marker
+pub struct MarkerPositions<'a> {
+    input: &'a [u8],
+    size: usize,
+    run: UniqueRun,
+}
+
+impl Iterator for MarkerPositions<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if self.size == 0 {
+            return None;
+        }
+        while let Some((&byte, rest)) = self.input.split_first() {
+            self.input = rest;
+            if self.run.push(byte) >= self.size {
+                return Some(self.run.position());
+            }
+        }
+        None
+    }
+}
+
+pub fn find_all_markers(input: &str, size: usize) -> MarkerPositions<'_> {
+    markers_in(input.as_bytes(), size)
+}
+
+fn markers_in(input: &[u8], size: usize) -> MarkerPositions<'_> {
+    MarkerPositions {
+        input,
+        size,
+        run: UniqueRun::new(),
+    }
+}
+
+// one message from a datastream: the marker that announced it and everything up to the next marker.
+// the content is bytes, since a marker can end in the middle of a multi-byte character
+#[derive(Debug, PartialEq)]
+pub struct Segment<'a> {
+    pub marker: Range<usize>,
+    pub body: Range<usize>,
+    pub content: &'a [u8],
+}
+
+// splits a datastream into messages, each one starting with a marker of [size] unique bytes.
+// once a marker is found the search starts over after it, so the next marker can't overlap the previous one
+// and the body in between is whatever comes before the next marker starts.
+// anything before the first marker is noise and gets dropped
+pub struct Segments<'a> {
+    input: &'a [u8],
+    size: usize,
+    next_marker: Option<usize>, // where the marker that starts the next segment ends
+}
+
+// finds the end of the first marker at or after [start]
+fn find_marker_from(input: &[u8], size: usize, start: usize) -> Option<usize> {
+    markers_in(&input[start..], size)
+        .next()
+        .map(|position| start + position)
+}
+
+impl<'a> Iterator for Segments<'a> {
+    type Item = Segment<'a>;
+
+    fn next(&mut self) -> Option<Segment<'a>> {
+        let marker_end = self.next_marker?;
+        self.next_marker = find_marker_from(self.input, self.size, marker_end);
+        let body_end = match self.next_marker {
+            Some(next_end) => next_end - self.size,
+            None => self.input.len(),
+        };
+        Some(Segment {
+            marker: marker_end - self.size..marker_end,
+            body: marker_end..body_end,
+            content: &self.input[marker_end..body_end],
+        })
+    }
+}
+
+// works on bytes, so the positions are byte offsets even if the input isn't ascii
+pub fn segment(input: &str, size: usize) -> Segments<'_> {
+    let input = input.as_bytes();
+    Segments {
+        input,
+        size,
+        next_marker: find_marker_from(input, size, 0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_all_markers() {
+        let positions: Vec<usize> = find_all_markers("mjqjpqmgbljsphdztnvjfqwrcgsmlb", 4).collect();
+        assert_eq!(positions[..4], [7, 8, 9, 10]);
+        assert_eq!(
+            find_all_markers("aabcbcdd", 3).collect::<Vec<_>>(),
+            vec![4, 7]
+        );
+        assert_eq!(find_all_markers("aaaa", 2).next(), None);
+        assert_eq!(find_all_markers("abcd", 0).next(), None);
+    }
+
+    #[test]
+    fn test_find_all_matches_first() {
+        for input in [
+            "bvwbjplbgvbhsrlpgdmjqwftvncz",
+            "nppdvjthqldpwncqszvftbrmjlhg",
+            "nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg",
+            "zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw",
+        ] {
+            for size in [4, 14] {
+                assert_eq!(
+                    find_all_markers(input, size).next(),
+                    crate::find_marker(input, size)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_segment() {
+        let segments: Vec<Segment> = segment("aaabcLLLLdefGG", 3).collect();
+        assert_eq!(
+            segments,
+            vec![
+                Segment {
+                    marker: 2..5,
+                    body: 5..8,
+                    content: b"LLL"
+                },
+                // "def" would also be a marker, but it overlaps "Lde" which comes first
+                Segment {
+                    marker: 8..11,
+                    body: 11..14,
+                    content: b"fGG"
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_segment_sample() {
+        let input = "mjqjpqmgbljsphdztnvjfqwrcgsmlb";
+        let segments: Vec<Segment> = segment(input, 14).collect();
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].marker, 5..19);
+        assert_eq!(segments[0].content, b"jfqwrcgsmlb");
+    }
+
+    #[test]
+    fn test_segment_no_markers() {
+        assert_eq!(segment("aaaa", 2).count(), 0);
+        assert_eq!(segment("", 4).count(), 0);
+    }
+
+    #[test]
+    fn test_segment_multi_byte() {
+        // é is two bytes, and the marker ends between them
+        let segments: Vec<Segment> = segment("aaé€b", 2).collect();
+        assert_eq!(segments[0].marker, 1..3);
+        assert_eq!(segments[1].marker, 3..5);
+        assert_eq!(segments.len(), 3);
+        assert_eq!(segments[2].content, b"b");
+    }
+}
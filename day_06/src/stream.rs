@@ -0,0 +1,195 @@
+use std::io::{self, ErrorKind, Read};
+
+// keeps track of where each byte was last seen, and where the current run of unique bytes starts.
+// when a byte repeats, the run has to start just after its previous occurrence.
+pub struct UniqueRun {
+    last_seen: [usize; 256], // position (index + 1) of the last occurrence, 0 if never seen
+    run_start: usize,
+    position: usize, // number of bytes consumed so far
+}
+
+impl UniqueRun {
+    pub fn new() -> Self {
+        UniqueRun {
+            last_seen: [0; 256],
+            run_start: 0,
+            position: 0,
+        }
+    }
+
+    // consumes one byte, returns how many unique bytes there are in a row ending with this one
+    pub fn push(&mut self, byte: u8) -> usize {
+        self.position += 1;
+        self.run_start = self.run_start.max(self.last_seen[byte as usize]);
+        self.last_seen[byte as usize] = self.position;
+        self.position - self.run_start
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+}
+
+// finds a marker in a stream that arrives a piece at a time (from a socket, pipe, etc.)
+pub struct MarkerDetector {
+    size: usize,
+    run: UniqueRun,
+    marker: Option<usize>,
+}
+
+impl MarkerDetector {
+    pub fn new(size: usize) -> Self {
+        MarkerDetector {
+            size,
+            run: UniqueRun::new(),
+            marker: None,
+        }
+    }
+
+    // consumes one byte, returns the marker position if this byte completed it
+    pub fn push(&mut self, byte: u8) -> Option<usize> {
+        if self.is_done() {
+            return None;
+        }
+        if self.run.push(byte) >= self.size {
+            self.marker = Some(self.run.position());
+        }
+        self.marker
+    }
+
+    // consumes a chunk of the stream, returns the marker position if it was found somewhere in this chunk
+    pub fn feed(&mut self, chunk: &[u8]) -> Option<usize> {
+        chunk.iter().find_map(|&byte| self.push(byte))
+    }
+
+    pub fn marker(&self) -> Option<usize> {
+        self.marker
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.marker.is_some() || self.size == 0
+    }
+}
+
+// reads from [reader] until a marker of each size has been found (or the stream ends),
+// calling [on_marker] with the size and position of each marker as soon as it turns up
+pub fn read_markers(
+    mut reader: impl Read,
+    sizes: &[usize],
+    mut on_marker: impl FnMut(usize, usize),
+) -> io::Result<Vec<Option<usize>>> {
+    let mut detectors: Vec<MarkerDetector> = sizes
+        .iter()
+        .map(|&size| MarkerDetector::new(size))
+        .collect();
+    let mut buf = [0u8; 4096];
+    while !detectors.iter().all(MarkerDetector::is_done) {
+        let len = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(len) => len,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        for detector in detectors.iter_mut() {
+            if let Some(position) = detector.feed(&buf[..len]) {
+                on_marker(detector.size, position);
+            }
+        }
+    }
+    Ok(detectors.iter().map(MarkerDetector::marker).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLES: [(&str, usize, usize); 5] = [
+        ("mjqjpqmgbljsphdztnvjfqwrcgsmlb", 7, 19),
+        ("bvwbjplbgvbhsrlpgdmjqwftvncz", 5, 23),
+        ("nppdvjthqldpwncqszvftbrmjlhg", 6, 23),
+        ("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg", 10, 29),
+        ("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw", 11, 26),
+    ];
+
+    #[test]
+    fn test_every_split() {
+        for (input, packet, message) in SAMPLES {
+            let bytes = input.as_bytes();
+            for split in 0..=bytes.len() {
+                for (size, expected) in [(4, packet), (14, message)] {
+                    let mut detector = MarkerDetector::new(size);
+                    let first = detector.feed(&bytes[..split]);
+                    let second = detector.feed(&bytes[split..]);
+                    // it should turn up in whichever chunk it ends in, and only be reported once
+                    if expected <= split {
+                        assert_eq!((first, second), (Some(expected), None));
+                    } else {
+                        assert_eq!((first, second), (None, Some(expected)));
+                    }
+                    assert_eq!(detector.marker(), Some(expected));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_every_chunk_size() {
+        for (input, packet, message) in SAMPLES {
+            for chunk_size in 1..=input.len() {
+                let mut detectors = [MarkerDetector::new(4), MarkerDetector::new(14)];
+                let mut found = Vec::new();
+                for chunk in input.as_bytes().chunks(chunk_size) {
+                    for detector in detectors.iter_mut() {
+                        found.extend(detector.feed(chunk));
+                    }
+                }
+                assert_eq!(found, vec![packet, message]);
+            }
+        }
+    }
+
+    // hands out the input a few bytes at a time, like a slow pipe
+    struct Trickle<'a> {
+        input: &'a [u8],
+        chunk_size: usize,
+        reads: usize,
+    }
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = self.chunk_size.min(buf.len()).min(self.input.len());
+            buf[..len].copy_from_slice(&self.input[..len]);
+            self.input = &self.input[len..];
+            self.reads += 1;
+            Ok(len)
+        }
+    }
+
+    #[test]
+    fn test_read_markers() {
+        for (input, packet, message) in SAMPLES {
+            for chunk_size in 1..=input.len() {
+                let mut reader = Trickle {
+                    input: input.as_bytes(),
+                    chunk_size,
+                    reads: 0,
+                };
+                let mut events = Vec::new();
+                let markers = read_markers(&mut reader, &[4, 14], |size, position| {
+                    events.push((size, position))
+                })
+                .unwrap();
+                assert_eq!(markers, vec![Some(packet), Some(message)]);
+                assert_eq!(events, vec![(4, packet), (14, message)]);
+                // it should stop reading once both are found
+                assert_eq!(reader.reads, message.div_ceil(chunk_size));
+            }
+        }
+    }
+
+    #[test]
+    fn test_read_markers_not_found() {
+        let markers = read_markers("aabbcc".as_bytes(), &[2, 4], |_, _| {}).unwrap();
+        assert_eq!(markers, vec![Some(3), None]);
+    }
+}
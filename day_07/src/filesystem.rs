@@ -0,0 +1,426 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt,
+};
+
+#[derive(Debug, PartialEq)]
+pub enum Entry {
+    Dir(Dir),
+    File(usize),
+}
+
+// entries are kept sorted by name, same as the listing in the puzzle description
+#[derive(Debug, Default, PartialEq)]
+pub struct Dir {
+    pub entries: BTreeMap<String, Entry>,
+}
+
+// builds a path like "/a/e" out of the names of the dirs leading to it, the root is just "/"
+pub fn canonical_path(path: &[&str]) -> String {
+    format!("/{}", path.join("/"))
+}
+
+impl Dir {
+    pub fn dirs(&self) -> impl Iterator<Item = (&String, &Dir)> {
+        self.entries.iter().filter_map(|(name, entry)| match entry {
+            Entry::Dir(dir) => Some((name, dir)),
+            Entry::File(_) => None,
+        })
+    }
+
+    pub fn files(&self) -> impl Iterator<Item = (&String, usize)> {
+        self.entries.iter().filter_map(|(name, entry)| match entry {
+            Entry::File(size) => Some((name, *size)),
+            Entry::Dir(_) => None,
+        })
+    }
+
+    // finds a dir by the names of the dirs leading to it from here, creating any that don't exist yet.
+    // if one of the names is already a file, gives back how far along [path] it is
+    fn get_or_create(&mut self, path: &[&str]) -> Result<&mut Dir, usize> {
+        let mut dir = self;
+        for (i, name) in path.iter().enumerate() {
+            let entry = dir
+                .entries
+                .entry(name.to_string())
+                .or_insert_with(|| Entry::Dir(Dir::default()));
+            match entry {
+                Entry::Dir(next) => dir = next,
+                Entry::File(_) => return Err(i),
+            }
+        }
+        Ok(dir)
+    }
+
+    // the cumulative size of every dir (including this one), keyed by canonical path
+    pub fn sizes(&self) -> HashMap<String, usize> {
+        let mut sizes = HashMap::new();
+        self.collect_sizes(&mut Vec::new(), &mut sizes);
+        sizes
+    }
+
+    // works from the bottom up so that each dir's size is only added up once
+    fn collect_sizes<'a>(
+        &'a self,
+        path: &mut Vec<&'a str>,
+        sizes: &mut HashMap<String, usize>,
+    ) -> usize {
+        let mut total: usize = self.files().map(|(_, size)| size).sum();
+        for (name, dir) in self.dirs() {
+            path.push(name);
+            total += dir.collect_sizes(path, sizes);
+            path.pop();
+        }
+        sizes.insert(canonical_path(path), total);
+        total
+    }
+}
+
+// things in a transcript that don't add up, line numbers start at 1
+#[derive(Debug, PartialEq)]
+pub enum Issue {
+    DuplicateListing {
+        line: usize,
+        path: String,
+    },
+    ConflictingSize {
+        line: usize,
+        path: String,
+        was: usize,
+        now: usize,
+    },
+    CdAboveRoot {
+        line: usize,
+    },
+    UnknownDir {
+        line: usize,
+        path: String,
+    },
+    NotADir {
+        line: usize,
+        path: String,
+    },
+    UnrecognisedLine {
+        line: usize,
+        text: String,
+    },
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Issue::DuplicateListing { line, path } => {
+                write!(f, "line {}: {} has already been listed", line, path)
+            }
+            Issue::ConflictingSize {
+                line,
+                path,
+                was,
+                now,
+            } => write!(
+                f,
+                "line {}: {} is {} bytes, but it was {} before",
+                line, path, now, was
+            ),
+            Issue::CdAboveRoot { line } => write!(f, "line {}: cd .. from /", line),
+            Issue::UnknownDir { line, path } => {
+                write!(f, "line {}: cd into {} which was never listed", line, path)
+            }
+            Issue::NotADir { line, path } => {
+                write!(f, "line {}: {} is a file, not a dir", line, path)
+            }
+            Issue::UnrecognisedLine { line, text } => {
+                write!(
+                    f,
+                    "line {}: don't know what `{}` is, skipped it",
+                    line, text
+                )
+            }
+        }
+    }
+}
+
+// rebuilds the filesystem from a transcript of `$ cd` and `$ ls` commands along with their output,
+// skipping any lines it doesn't understand
+pub fn parse(input: &str) -> Dir {
+    parse_checked(input, true).0
+}
+
+// like parse, but also reports anything odd about the transcript.
+// with [dedupe] a file that shows up in more than one listing is only counted once (the latest size wins),
+// without it every listing is added up, which is what the original solution did
+pub fn parse_checked(input: &str, dedupe: bool) -> (Dir, Vec<Issue>) {
+    let mut root = Dir::default();
+    let mut issues = Vec::new();
+    let mut path: Vec<&str> = Vec::new();
+    let mut known_dirs: HashSet<String> = HashSet::from(["/".to_string()]);
+    let mut listed_dirs: HashSet<String> = HashSet::new();
+    let mut file_sizes: HashMap<String, usize> = HashMap::new();
+    for (index, line) in input.lines().map(|line| line.trim()).enumerate() {
+        let line_num = index + 1;
+        if let Some(dirname) = line.strip_prefix("$ cd ") {
+            match dirname {
+                "/" => path.clear(),
+                ".." => {
+                    if path.pop().is_none() {
+                        issues.push(Issue::CdAboveRoot { line: line_num });
+                    }
+                }
+                _ => {
+                    path.push(dirname);
+                    let dir_path = canonical_path(&path);
+                    // the path is kept anyway so that a `cd ..` afterwards still ends up in the right place,
+                    // but nothing gets listed into it
+                    if let Err(i) = root.get_or_create(&path) {
+                        issues.push(Issue::NotADir {
+                            line: line_num,
+                            path: canonical_path(&path[..=i]),
+                        });
+                    } else if !known_dirs.contains(&dir_path) {
+                        issues.push(Issue::UnknownDir {
+                            line: line_num,
+                            path: dir_path,
+                        });
+                    }
+                }
+            }
+        } else if line == "$ ls" {
+            let dir_path = canonical_path(&path);
+            if !listed_dirs.insert(dir_path.clone()) {
+                issues.push(Issue::DuplicateListing {
+                    line: line_num,
+                    path: dir_path,
+                });
+            }
+        } else if line.is_empty() {
+            continue;
+        } else if let Some(dirname) = line.strip_prefix("dir ") {
+            path.push(dirname);
+            match root.get_or_create(&path) {
+                Ok(_) => {
+                    known_dirs.insert(canonical_path(&path));
+                }
+                // anything further up was reported when it was cd'd into
+                Err(i) if i + 1 == path.len() => issues.push(Issue::NotADir {
+                    line: line_num,
+                    path: canonical_path(&path),
+                }),
+                Err(_) => {}
+            }
+            path.pop();
+        } else {
+            // anything else should be a file, otherwise it's some other command or its output
+            let file = line
+                .split_once(' ')
+                .and_then(|(size, name)| Some((size.parse::<usize>().ok()?, name)));
+            let Some((size, name)) = file else {
+                issues.push(Issue::UnrecognisedLine {
+                    line: line_num,
+                    text: line.to_string(),
+                });
+                continue;
+            };
+            path.push(name);
+            let file_path = canonical_path(&path);
+            path.pop();
+            let previous = file_sizes.insert(file_path.clone(), size);
+            if let Some(was) = previous.filter(|&was| was != size) {
+                issues.push(Issue::ConflictingSize {
+                    line: line_num,
+                    path: file_path,
+                    was,
+                    now: size,
+                });
+            }
+            let Ok(dir) = root.get_or_create(&path) else {
+                continue;
+            };
+            let entries = &mut dir.entries;
+            let size = match entries.get(name) {
+                Some(Entry::File(counted)) if !dedupe => counted + size,
+                _ => size,
+            };
+            entries.insert(name.to_string(), Entry::File(size));
+        }
+    }
+    (root, issues)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sample() {
+        let root = parse(include_str!("input-sample.txt"));
+        assert_eq!(
+            root.dirs()
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["a", "d"]
+        );
+        assert_eq!(
+            root.files().collect::<Vec<_>>(),
+            vec![
+                (&"b.txt".to_string(), 14848514),
+                (&"c.dat".to_string(), 8504156)
+            ]
+        );
+        let Some(Entry::Dir(a)) = root.entries.get("a") else {
+            panic!("a should be a dir")
+        };
+        assert_eq!(a.entries.get("f"), Some(&Entry::File(29116)));
+        assert_eq!(a.sizes()["/"], 94853);
+    }
+
+    #[test]
+    fn test_empty_dirs_are_kept() {
+        let root = parse(
+            "$ cd /
+            $ ls
+            dir empty
+            1 file",
+        );
+        assert_eq!(root.entries.get("empty"), Some(&Entry::Dir(Dir::default())));
+        assert_eq!(root.sizes()["/empty"], 0);
+    }
+
+    #[test]
+    fn test_canonical_path() {
+        assert_eq!(canonical_path(&[]), "/");
+        assert_eq!(canonical_path(&["a", "e"]), "/a/e");
+    }
+
+    const MESSY: &str = "$ cd /
+        $ ls
+        dir a
+        10 b
+        $ cd a
+        $ ls
+        5 c
+        $ cd ..
+        $ ls
+        dir a
+        12 b
+        $ cd ..
+        $ cd x
+        $ ls
+        1 y";
+
+    #[test]
+    fn test_clean_transcripts() {
+        let (_, issues) = parse_checked(include_str!("input-sample.txt"), true);
+        assert_eq!(issues, vec![]);
+        let (_, issues) = parse_checked(include_str!("input.txt"), true);
+        assert_eq!(issues, vec![]);
+    }
+
+    #[test]
+    fn test_issues() {
+        let (_, issues) = parse_checked(MESSY, true);
+        assert_eq!(
+            issues,
+            vec![
+                Issue::DuplicateListing {
+                    line: 9,
+                    path: "/".to_string()
+                },
+                Issue::ConflictingSize {
+                    line: 11,
+                    path: "/b".to_string(),
+                    was: 10,
+                    now: 12
+                },
+                Issue::CdAboveRoot { line: 12 },
+                Issue::UnknownDir {
+                    line: 13,
+                    path: "/x".to_string()
+                },
+            ]
+        );
+        assert_eq!(
+            issues[1].to_string(),
+            "line 11: /b is 12 bytes, but it was 10 before"
+        );
+    }
+
+    #[test]
+    fn test_file_used_as_dir() {
+        let (root, issues) = parse_checked(
+            "$ cd /
+            $ ls
+            123 a
+            $ cd a
+            $ ls
+            dir b
+            4 c
+            $ cd b
+            $ cd ..
+            $ cd ..
+            $ ls
+            dir a
+            5 d",
+            true,
+        );
+        let not_a_dir = |line: usize| Issue::NotADir {
+            line,
+            path: "/a".to_string(),
+        };
+        assert_eq!(
+            issues,
+            vec![
+                not_a_dir(4),
+                not_a_dir(8),
+                Issue::DuplicateListing {
+                    line: 11,
+                    path: "/".to_string()
+                },
+                not_a_dir(12)
+            ]
+        );
+        assert_eq!(issues[0].to_string(), "line 4: /a is a file, not a dir");
+        assert_eq!(root.sizes()["/"], 128);
+    }
+
+    #[test]
+    fn test_unrecognised_lines() {
+        let (root, issues) = parse_checked(
+            "$ cd /
+            $ ls
+            10 a
+            $ ls -la
+            $ pwd
+            $ cd
+            what's this
+            ten b",
+            true,
+        );
+        let unrecognised = |line: usize, text: &str| Issue::UnrecognisedLine {
+            line,
+            text: text.to_string(),
+        };
+        assert_eq!(
+            issues,
+            vec![
+                unrecognised(4, "$ ls -la"),
+                unrecognised(5, "$ pwd"),
+                unrecognised(6, "$ cd"),
+                unrecognised(7, "what's this"),
+                unrecognised(8, "ten b"),
+            ]
+        );
+        assert_eq!(
+            issues[0].to_string(),
+            "line 4: don't know what `$ ls -la` is, skipped it"
+        );
+        assert_eq!(root.sizes()["/"], 10);
+    }
+
+    #[test]
+    fn test_dedupe() {
+        let (root, _) = parse_checked(MESSY, true);
+        assert_eq!(root.sizes()["/"], 18);
+        let (root, _) = parse_checked(MESSY, false);
+        assert_eq!(root.sizes()["/"], 28);
+    }
+}
@@ -0,0 +1,211 @@
+use std::{collections::HashMap, fs, io, path::Path};
+
+use crate::filesystem::{canonical_path, Dir, Entry};
+
+// a filesystem along with the size of every dir, worked out while it was being built
+// so there's something independent of the parser to check against
+pub struct Walk {
+    pub root: Dir,
+    pub sizes: HashMap<String, usize>,
+}
+
+// reads a real directory from the local disk. symlinks and names that aren't utf-8 are skipped
+pub fn walk_local(dir: &Path) -> io::Result<Walk> {
+    let mut sizes = HashMap::new();
+    let root = walk_local_dir(dir, &mut Vec::new(), &mut sizes)?;
+    Ok(Walk { root, sizes })
+}
+
+fn walk_local_dir(
+    dir: &Path,
+    path: &mut Vec<String>,
+    sizes: &mut HashMap<String, usize>,
+) -> io::Result<Dir> {
+    let mut result = Dir::default();
+    let mut total = 0;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let Ok(name) = entry.file_name().into_string() else {
+            continue;
+        };
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            path.push(name.clone());
+            let child = walk_local_dir(&entry.path(), path, sizes)?;
+            total += sizes[&canonical_path(&path_refs(path))];
+            path.pop();
+            result.entries.insert(name, Entry::Dir(child));
+        } else if file_type.is_file() {
+            let size = entry.metadata()?.len() as usize;
+            total += size;
+            result.entries.insert(name, Entry::File(size));
+        }
+    }
+    sizes.insert(canonical_path(&path_refs(path)), total);
+    Ok(result)
+}
+
+fn path_refs(path: &[String]) -> Vec<&str> {
+    path.iter().map(String::as_str).collect()
+}
+
+// xorshift, just enough randomness to generate test trees without pulling in a crate
+fn next_random(state: &mut u64) -> u64 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    *state
+}
+
+// a made up filesystem, the same [seed] always gives the same tree
+pub fn random_tree(seed: u64, max_depth: usize) -> Walk {
+    let mut state = seed.max(1);
+    let mut sizes = HashMap::new();
+    let root = random_dir(&mut state, max_depth, &mut Vec::new(), &mut sizes);
+    Walk { root, sizes }
+}
+
+fn random_name(state: &mut u64) -> String {
+    let len = 1 + next_random(state) % 8;
+    let mut name: String = (0..len)
+        .map(|_| (b'a' + (next_random(state) % 26) as u8) as char)
+        .collect();
+    if next_random(state).is_multiple_of(3) {
+        let extensions = ["txt", "dat", "lst", "log", "ext"];
+        name = format!(
+            "{}.{}",
+            name,
+            extensions[(next_random(state) % extensions.len() as u64) as usize]
+        );
+    }
+    name
+}
+
+fn random_dir(
+    state: &mut u64,
+    depth: usize,
+    path: &mut Vec<String>,
+    sizes: &mut HashMap<String, usize>,
+) -> Dir {
+    let mut result = Dir::default();
+    let mut total = 0;
+    let num_files = next_random(state) % 6;
+    for _ in 0..num_files {
+        let name = random_name(state);
+        if result.entries.contains_key(&name) {
+            continue;
+        }
+        let size = 1 + (next_random(state) % 300000) as usize;
+        total += size;
+        result.entries.insert(name, Entry::File(size));
+    }
+    let num_dirs = if depth == 0 {
+        0
+    } else {
+        next_random(state) % 4
+    };
+    for _ in 0..num_dirs {
+        let name = random_name(state);
+        if result.entries.contains_key(&name) {
+            continue;
+        }
+        path.push(name.clone());
+        let child = random_dir(state, depth - 1, path, sizes);
+        total += sizes[&canonical_path(&path_refs(path))];
+        path.pop();
+        result.entries.insert(name, Entry::Dir(child));
+    }
+    sizes.insert(canonical_path(&path_refs(path)), total);
+    result
+}
+
+// writes out the `$ cd` / `$ ls` session that would explore the whole tree, in the puzzle's format
+pub fn transcript(root: &Dir) -> String {
+    let mut lines = vec!["$ cd /".to_string()];
+    transcript_dir(root, &mut lines);
+    lines.join("\n")
+}
+
+fn transcript_dir(dir: &Dir, lines: &mut Vec<String>) {
+    lines.push("$ ls".to_string());
+    for (name, entry) in dir.entries.iter() {
+        lines.push(match entry {
+            Entry::Dir(_) => format!("dir {}", name),
+            Entry::File(size) => format!("{} {}", size, name),
+        });
+    }
+    for (name, child) in dir.dirs() {
+        lines.push(format!("$ cd {}", name));
+        transcript_dir(child, lines);
+        lines.push("$ cd ..".to_string());
+    }
+}
+
+// runs the transcript for [walk] back through the parser and lists any dirs where the sizes disagree
+pub fn cross_check(walk: &Walk) -> Vec<String> {
+    let parsed = crate::get_dir_sizes_cumlative(&transcript(&walk.root));
+    let mut mismatches: Vec<String> = walk
+        .sizes
+        .iter()
+        .filter(|(path, size)| parsed.get(*path) != Some(size))
+        .map(|(path, size)| {
+            format!(
+                "{}: walked {} but parsed {:?}",
+                path,
+                size,
+                parsed.get(path)
+            )
+        })
+        .collect();
+    mismatches.extend(
+        parsed
+            .keys()
+            .filter(|path| !walk.sizes.contains_key(*path))
+            .map(|path| format!("{}: parsed but never walked", path)),
+    );
+    mismatches.sort();
+    mismatches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::parse;
+
+    #[test]
+    fn test_sample_transcript_round_trip() {
+        let root = parse(include_str!("input-sample.txt"));
+        assert_eq!(parse(&transcript(&root)), root);
+    }
+
+    #[test]
+    fn test_random_trees() {
+        for seed in 1..30 {
+            let walk = random_tree(seed, 4);
+            assert_eq!(parse(&transcript(&walk.root)), walk.root);
+            assert_eq!(cross_check(&walk), Vec::<String>::new());
+        }
+    }
+
+    #[test]
+    fn test_random_tree_is_repeatable() {
+        assert_eq!(random_tree(7, 3).root, random_tree(7, 3).root);
+    }
+
+    #[test]
+    fn test_walk_local() {
+        let dir = std::env::temp_dir().join(format!("day_07_walk_{}", std::process::id()));
+        fs::create_dir_all(dir.join("a/e")).unwrap();
+        fs::create_dir_all(dir.join("empty")).unwrap();
+        fs::write(dir.join("b.txt"), "hello").unwrap();
+        fs::write(dir.join("a/f"), "abc").unwrap();
+        fs::write(dir.join("a/e/i"), [0u8; 584]).unwrap();
+        let walk = walk_local(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+        let walk = walk.unwrap();
+        assert_eq!(walk.sizes["/"], 592);
+        assert_eq!(walk.sizes["/a"], 587);
+        assert_eq!(walk.sizes["/empty"], 0);
+        assert_eq!(cross_check(&walk), Vec::<String>::new());
+    }
+}
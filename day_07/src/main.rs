@@ -1,40 +1,13 @@
+mod filesystem;
+mod generate;
+mod planner;
+mod render;
+
 use std::collections::HashMap;
 
+// cumulative size of every dir, keyed by canonical path ("/", "/a", "/a/e", etc.)
 fn get_dir_sizes_cumlative(input: &str) -> HashMap<String, usize> {
-    let mut dirs: HashMap<String, usize> = HashMap::new();
-    let mut path: Vec<&str> = Vec::new();
-    for line in input.lines() {
-        //println!("parsing '{}'", line);
-        if line.starts_with("$ cd") {
-            let dirname = line.split_at(5).1;
-            if dirname == "/" {
-                path.clear();
-                path.push(dirname);
-            } else if dirname == ".." {
-                path.pop();
-            } else {
-                path.push(dirname);
-            }
-            //println!("path: {}", path.join("/"));
-        } else {
-            if let Some((left, _)) = line.split_once(" ") {
-                if let Ok(file_size) = left.parse::<usize>() {
-                    //println!("size {}", size);
-                    for i in 0..path.len() {
-                        let dir = path[0..=i].join("/");
-                        //println!("part {}: {:?}", i, dir);
-                        let dir_size = match dirs.get(&dir) {
-                            Some(dir_size) => dir_size.to_owned(),
-                            None => 0,
-                        };
-                        dirs.insert(dir, dir_size + file_size);
-                    }
-                }
-                // else it's a ls command or a directory in ls results
-            }
-        }
-    }
-    dirs
+    filesystem::parse(input).sizes()
 }
 
 fn sum_dirs_under_100k(dirs: &HashMap<String, usize>) -> usize {
@@ -42,17 +15,16 @@ fn sum_dirs_under_100k(dirs: &HashMap<String, usize>) -> usize {
     dirs.values().filter(|&&size| size < max).sum()
 }
 
+const DISK_SIZE: usize = 70000000;
+const UPDATE_SIZE: usize = 30000000;
+
 fn get_needed_space(dirs: &HashMap<String, usize>) -> usize {
-    let disk_size: usize = 70000000;
-    let update_size: usize = 30000000;
     let used_space = dirs.get("/").expect("needs a / dir");
-    let unused_space = disk_size - used_space;
-    let needed_space = update_size - unused_space;
-    needed_space
+    planner::needed_space(*used_space, DISK_SIZE, UPDATE_SIZE)
 }
 
 fn find_size_to_delete(dirs: &HashMap<String, usize>) -> usize {
-    let needed_space = get_needed_space(&dirs);
+    let needed_space = get_needed_space(dirs);
     dirs.values().fold(usize::MAX, |accum, item| {
         if item > &needed_space && item < &accum {
             *item
@@ -65,6 +37,73 @@ fn find_size_to_delete(dirs: &HashMap<String, usize>) -> usize {
 fn main() {
     let input = include_str!("input.txt");
 
+    // `cargo run -- tree` draws the filesystem, `cargo run -- du [depth]` lists the biggest dirs,
+    // `cargo run -- check` points out anything odd in the transcript,
+    // `cargo run -- plan [disk size] [required space]` suggests what to delete,
+    // `cargo run -- generate <dir | seed>` writes a transcript for a real dir or a random tree,
+    // `cargo run -- verify <dir | seed>` does the same but checks the parsed sizes against it instead
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("tree") => {
+            println!("{}", render::render_tree(&filesystem::parse(input), true));
+            return;
+        }
+        Some("du") => {
+            let max_depth = args
+                .get(1)
+                .map(|depth| depth.parse().expect("depth must be a number"));
+            println!(
+                "{}",
+                render::du_report(&filesystem::parse(input), max_depth)
+            );
+            return;
+        }
+        Some("check") => {
+            let (_, issues) = filesystem::parse_checked(input, true);
+            for issue in issues.iter() {
+                println!("{}", issue);
+            }
+            println!("{} issues found", issues.len());
+            return;
+        }
+        Some("plan") => {
+            let root = filesystem::parse(input);
+            let size_arg = |i: usize, default: usize| {
+                args.get(i)
+                    .map_or(default, |size| size.parse().expect("sizes must be numbers"))
+            };
+            let planner =
+                planner::Planner::new(&root, size_arg(1, DISK_SIZE), size_arg(2, UPDATE_SIZE));
+            println!("space needed: {}", planner.needed_space());
+            println!("smallest single dir: {:?}", planner.smallest_dir());
+            println!("smallest set of dirs: {:?}", planner.smallest_set());
+            return;
+        }
+        Some(command @ ("generate" | "verify")) => {
+            let source = args.get(1).expect("needs a directory or a seed");
+            let walk = match source.parse() {
+                Ok(seed) => generate::random_tree(seed, 6),
+                Err(_) => generate::walk_local(std::path::Path::new(source))
+                    .expect("failed to read directory"),
+            };
+            if command == "generate" {
+                println!("{}", generate::transcript(&walk.root));
+                return;
+            }
+            let mismatches = generate::cross_check(&walk);
+            for mismatch in mismatches.iter() {
+                println!("{}", mismatch);
+            }
+            println!(
+                "{} dirs checked, {} mismatches",
+                walk.sizes.len(),
+                mismatches.len()
+            );
+            return;
+        }
+        _ => {}
+    }
+
     let dir_sizes = get_dir_sizes_cumlative(input);
     println!(
         "total size of directories under 100k: {:?}",
@@ -80,9 +119,9 @@ mod tests {
     fn get_sample_dirs() -> HashMap<String, usize> {
         HashMap::from([
             ("/".to_string(), 48381165),
-            ("//a".to_string(), 94853),
-            ("//d".to_string(), 24933642),
-            ("//a/e".to_string(), 584),
+            ("/a".to_string(), 94853),
+            ("/d".to_string(), 24933642),
+            ("/a/e".to_string(), 584),
         ])
     }
 
@@ -92,6 +131,14 @@ mod tests {
         assert_eq!(get_dir_sizes_cumlative(input), get_sample_dirs());
     }
 
+    #[test]
+    fn test_parse_skips_unknown_lines() {
+        // like the original, anything that isn't a cd, an ls or a listing is ignored
+        let input =
+            include_str!("input-sample.txt").replace("$ ls\n", "$ ls\n$ pwd\nstray text\n$ cd\n");
+        assert_eq!(get_dir_sizes_cumlative(&input), get_sample_dirs());
+    }
+
     #[test]
     fn test_sizes() {
         assert_eq!(sum_dirs_under_100k(&get_sample_dirs()), 95437);
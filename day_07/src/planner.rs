@@ -0,0 +1,304 @@
+use std::collections::HashMap;
+
+use crate::filesystem::{canonical_path, Dir};
+
+// how much more space is needed, this doesn't underflow if the disk is already overfull
+pub fn needed_space(used_space: usize, disk_size: usize, required_space: usize) -> usize {
+    (used_space + required_space).saturating_sub(disk_size)
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Cleanup {
+    pub paths: Vec<String>,
+    pub freed: usize,
+}
+
+// works out what to delete to get [required_space] free on a disk of [disk_size].
+// the root dir can't be deleted, everything else is fair game
+pub struct Planner<'a> {
+    root: &'a Dir,
+    disk_size: usize,
+    required_space: usize,
+}
+
+impl<'a> Planner<'a> {
+    pub fn new(root: &'a Dir, disk_size: usize, required_space: usize) -> Self {
+        Planner {
+            root,
+            disk_size,
+            required_space,
+        }
+    }
+
+    pub fn needed_space(&self) -> usize {
+        needed_space(self.root.sizes()["/"], self.disk_size, self.required_space)
+    }
+
+    // the smallest single dir that frees up enough space on its own, nothing at all if nothing is needed
+    pub fn smallest_dir(&self) -> Option<Cleanup> {
+        let needed = self.needed_space();
+        if needed == 0 {
+            return Some(Cleanup {
+                paths: vec![],
+                freed: 0,
+            });
+        }
+        self.root
+            .sizes()
+            .into_iter()
+            .filter(|(path, size)| path != "/" && *size >= needed)
+            .min_by(|(a_path, a_size), (b_path, b_size)| {
+                a_size.cmp(b_size).then_with(|| a_path.cmp(b_path))
+            })
+            .map(|(path, freed)| Cleanup {
+                paths: vec![path],
+                freed,
+            })
+    }
+
+    // the fewest dirs that free up enough space between them, and out of those the ones that delete the least.
+    // none of them are inside each other, since deleting a dir deletes everything in it anyway
+    pub fn smallest_set(&self) -> Option<Cleanup> {
+        let needed = self.needed_space();
+        if needed == 0 {
+            return Some(Cleanup {
+                paths: vec![],
+                freed: 0,
+            });
+        }
+        let sizes = self.root.sizes();
+        let mut tree = Tree {
+            dirs: Vec::new(),
+            needed,
+            total: 0,
+            count: 0,
+        };
+        let top: Vec<usize> = self
+            .root
+            .dirs()
+            .map(|(name, dir)| tree.add(dir, &mut vec![name.as_str()], &sizes))
+            .collect();
+
+        // a dir always frees at least as much as anything inside it, so the most that k dirs can free
+        // is the k biggest ones at the top, and that's how many it takes
+        let mut biggest: Vec<usize> = top.iter().map(|&i| tree.dirs[i].size).collect();
+        biggest.sort_unstable_by(|a, b| b.cmp(a));
+        tree.total = biggest.iter().sum();
+        if tree.total < needed {
+            return None;
+        }
+        let mut freed = 0;
+        tree.count = biggest.iter().position(|&size| {
+            freed += size;
+            freed >= needed
+        })? + 1;
+
+        let options = tree.combine(&top);
+        let (freed, chosen) = options[tree.count].first()?;
+        let mut paths: Vec<String> = chosen.iter().map(|&i| tree.dirs[i].path.clone()).collect();
+        paths.sort();
+        Some(Cleanup {
+            paths,
+            freed: *freed,
+        })
+    }
+}
+
+struct TreeDir {
+    path: String,
+    size: usize,
+    children: Vec<usize>,
+}
+
+// what can be freed with each number of dirs, smallest first, along with the dirs that free it
+type Options = Vec<Vec<(usize, Vec<usize>)>>;
+
+// dynamic programming from the bottom of the tree up. each dir can be deleted itself,
+// or some of the dirs inside it, but never both
+struct Tree {
+    dirs: Vec<TreeDir>,
+    needed: usize,
+    total: usize, // everything that can be deleted
+    count: usize, // how many dirs it takes, so there's no point looking at more
+}
+
+impl Tree {
+    fn add<'a>(
+        &mut self,
+        dir: &'a Dir,
+        path: &mut Vec<&'a str>,
+        sizes: &HashMap<String, usize>,
+    ) -> usize {
+        let children = dir
+            .dirs()
+            .map(|(name, child)| {
+                path.push(name);
+                let index = self.add(child, path, sizes);
+                path.pop();
+                index
+            })
+            .collect();
+        let path = canonical_path(path);
+        self.dirs.push(TreeDir {
+            size: sizes[&path],
+            path,
+            children,
+        });
+        self.dirs.len() - 1
+    }
+
+    // the options for deleting things inside any of [dirs]
+    fn combine(&self, dirs: &[usize]) -> Options {
+        let mut options: Options = vec![Vec::new(); self.count + 1];
+        options[0].push((0, Vec::new()));
+        let mut outside = self.total;
+        for &dir in dirs {
+            let theirs = self.options(dir);
+            outside -= self.dirs[dir].size;
+            let mut combined: Options = vec![Vec::new(); self.count + 1];
+            for (count, ours) in options.iter().enumerate() {
+                for (more, theirs) in theirs.iter().enumerate().take(self.count + 1 - count) {
+                    for (freed, chosen) in ours {
+                        for (extra, also) in theirs {
+                            let chosen = chosen.iter().chain(also).copied().collect();
+                            combined[count + more].push((freed + extra, chosen));
+                        }
+                    }
+                }
+            }
+            options = combined;
+            self.prune(&mut options, outside);
+        }
+        options
+    }
+
+    fn options(&self, dir: usize) -> Options {
+        let mut options = self.combine(&self.dirs[dir].children);
+        if self.count > 0 {
+            options[1].push((self.dirs[dir].size, vec![dir]));
+        }
+        options
+    }
+
+    // drops anything that couldn't get to [needed] even if everything [outside] was deleted too,
+    // and anything more than the least that gets there on its own
+    fn prune(&self, options: &mut Options, outside: usize) {
+        let least = self.needed.saturating_sub(outside);
+        for options in options.iter_mut() {
+            options.retain(|(freed, _)| *freed >= least);
+            options.sort_by_key(|(freed, _)| *freed);
+            options.dedup_by_key(|(freed, _)| *freed);
+            if let Some(enough) = options.iter().position(|(freed, _)| *freed >= self.needed) {
+                options.truncate(enough + 1);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::parse;
+
+    #[test]
+    fn test_needed_space() {
+        assert_eq!(needed_space(48381165, 70000000, 30000000), 8381165);
+        assert_eq!(needed_space(100, 1000, 500), 0);
+        assert_eq!(needed_space(1200, 1000, 500), 700); // overfull
+    }
+
+    #[test]
+    fn test_sample() {
+        let root = parse(include_str!("input-sample.txt"));
+        let planner = Planner::new(&root, 70000000, 30000000);
+        assert_eq!(planner.needed_space(), 8381165);
+        let single = Cleanup {
+            paths: vec!["/d".to_string()],
+            freed: 24933642,
+        };
+        assert_eq!(planner.smallest_dir(), Some(single));
+        assert_eq!(
+            planner.smallest_set(),
+            Some(Cleanup {
+                paths: vec!["/d".to_string()],
+                freed: 24933642,
+            })
+        );
+    }
+
+    #[test]
+    fn test_smallest_set() {
+        let root = parse(
+            "$ cd /
+            $ ls
+            dir a
+            dir b
+            dir c
+            $ cd a
+            $ ls
+            dir x
+            40 big
+            $ cd x
+            $ ls
+            30 f
+            $ cd /
+            $ cd b
+            $ ls
+            35 f
+            $ cd /
+            $ cd c
+            $ ls
+            100 f",
+        );
+        // 205 used, so 65 is needed, and a single dir can do that
+        let planner = Planner::new(&root, 200, 60);
+        let single = Cleanup {
+            paths: vec!["/a".to_string()],
+            freed: 70,
+        };
+        assert_eq!(planner.smallest_dir(), Some(single));
+        assert_eq!(
+            planner.smallest_set(),
+            Some(Cleanup {
+                paths: vec!["/a".to_string()],
+                freed: 70
+            })
+        );
+        // 125 needed is more than any one dir, a + x would be 140 but they're nested
+        let planner = Planner::new(&root, 200, 120);
+        assert_eq!(planner.smallest_dir(), None);
+        assert_eq!(
+            planner.smallest_set(),
+            Some(Cleanup {
+                paths: vec!["/a/x".to_string(), "/c".to_string()],
+                freed: 130
+            })
+        );
+        // everything has to go, or it's more than everything
+        let planner = Planner::new(&root, 200, 200);
+        assert_eq!(
+            planner.smallest_set().map(|cleanup| cleanup.freed),
+            Some(205)
+        );
+        assert_eq!(Planner::new(&root, 200, 201).smallest_set(), None);
+    }
+
+    #[test]
+    fn test_nothing_needed_or_impossible() {
+        let root = parse("$ cd /\n$ ls\n10 f");
+        assert_eq!(
+            Planner::new(&root, 100, 10).smallest_set(),
+            Some(Cleanup {
+                paths: vec![],
+                freed: 0
+            })
+        );
+        assert_eq!(
+            Planner::new(&root, 100, 10).smallest_dir(),
+            Planner::new(&root, 100, 10).smallest_set()
+        );
+        // the only file is in the root, which can't be deleted
+        assert_eq!(Planner::new(&root, 10, 10).smallest_set(), None);
+        assert_eq!(Planner::new(&root, 10, 10).smallest_dir(), None);
+    }
+}
@@ -0,0 +1,162 @@
+use crate::filesystem::{canonical_path, Dir, Entry};
+
+// draws the filesystem the same way the puzzle description does, e.g.
+// - / (dir)
+//   - a (dir)
+//     - f (file, size=29116)
+// with [dir_sizes] each dir gets its cumulative size too
+pub fn render_tree(root: &Dir, dir_sizes: bool) -> String {
+    let mut lines = Vec::new();
+    render_dir(root, "/", 0, dir_sizes, &mut lines);
+    lines.join("\n")
+}
+
+// returns the size of [dir] so that parents don't have to add it up again
+fn render_dir(
+    dir: &Dir,
+    name: &str,
+    depth: usize,
+    dir_sizes: bool,
+    lines: &mut Vec<String>,
+) -> usize {
+    let index = lines.len();
+    lines.push(String::new()); // filled in once the size is known
+    let indent = "  ".repeat(depth + 1);
+    let mut total = 0;
+    for (child_name, entry) in dir.entries.iter() {
+        total += match entry {
+            Entry::Dir(child) => render_dir(child, child_name, depth + 1, dir_sizes, lines),
+            Entry::File(size) => {
+                lines.push(format!("{}- {} (file, size={})", indent, child_name, size));
+                *size
+            }
+        };
+    }
+    lines[index] = if dir_sizes {
+        format!("{}- {} (dir, size={})", &indent[2..], name, total)
+    } else {
+        format!("{}- {} (dir)", &indent[2..], name)
+    };
+    total
+}
+
+// formats a size the way `du -h` does: 1024 based, one decimal place when it's under 10,
+// always rounded up so that it never looks smaller than it is
+pub fn human_size(size: usize) -> String {
+    let units = ["B", "K", "M", "G", "T", "P"];
+    let mut value = size as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < units.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    let tenths = (value * 10.0).ceil();
+    if unit == 0 {
+        format!("{}{}", size, units[0])
+    } else if tenths < 100.0 {
+        format!("{:.1}{}", tenths / 10.0, units[unit])
+    } else if value.ceil() >= 1024.0 && unit + 1 < units.len() {
+        // rounding up took it into the next unit
+        format!("1.0{}", units[unit + 1])
+    } else {
+        format!("{:.0}{}", value.ceil(), units[unit])
+    }
+}
+
+// a `du -h --max-depth` style report, biggest dirs first (ties are sorted by path).
+// [max_depth] of 0 is just the root, None shows everything
+pub fn du_report(root: &Dir, max_depth: Option<usize>) -> String {
+    let mut rows: Vec<(usize, String)> = Vec::new();
+    collect_rows(root, &mut Vec::new(), max_depth, &mut rows);
+    rows.sort_by(|(a_size, a_path), (b_size, b_path)| {
+        b_size.cmp(a_size).then_with(|| a_path.cmp(b_path))
+    });
+    rows.iter()
+        .map(|(size, path)| format!("{:>6}  {}", human_size(*size), path))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn collect_rows<'a>(
+    dir: &'a Dir,
+    path: &mut Vec<&'a str>,
+    max_depth: Option<usize>,
+    rows: &mut Vec<(usize, String)>,
+) -> usize {
+    let mut total: usize = dir.files().map(|(_, size)| size).sum();
+    for (name, child) in dir.dirs() {
+        path.push(name);
+        total += collect_rows(child, path, max_depth, rows);
+        path.pop();
+    }
+    if max_depth.is_none_or(|max_depth| path.len() <= max_depth) {
+        rows.push((total, canonical_path(path)));
+    }
+    total
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::parse;
+
+    #[test]
+    fn test_render_tree() {
+        let root = parse(include_str!("input-sample.txt"));
+        // straight from the puzzle description
+        assert_eq!(
+            render_tree(&root, false),
+            "- / (dir)
+  - a (dir)
+    - e (dir)
+      - i (file, size=584)
+    - f (file, size=29116)
+    - g (file, size=2557)
+    - h.lst (file, size=62596)
+  - b.txt (file, size=14848514)
+  - c.dat (file, size=8504156)
+  - d (dir)
+    - d.ext (file, size=5626152)
+    - d.log (file, size=8033020)
+    - j (file, size=4060174)
+    - k (file, size=7214296)"
+        );
+    }
+
+    #[test]
+    fn test_render_tree_with_sizes() {
+        let root = parse(include_str!("input-sample.txt"));
+        let rendered = render_tree(&root, true);
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert_eq!(lines[0], "- / (dir, size=48381165)");
+        assert_eq!(lines[1], "  - a (dir, size=94853)");
+        assert_eq!(lines[2], "    - e (dir, size=584)");
+    }
+
+    #[test]
+    fn test_human_size() {
+        assert_eq!(human_size(0), "0B");
+        assert_eq!(human_size(584), "584B");
+        assert_eq!(human_size(94853), "93K");
+        assert_eq!(human_size(4060174), "3.9M");
+        assert_eq!(human_size(48381165), "47M");
+        assert_eq!(human_size(1025), "1.1K");
+        assert_eq!(human_size(10189), "10K");
+        assert_eq!(human_size(1024 * 1024 - 1), "1.0M");
+        assert_eq!(human_size(70000000 * 1024), "67G");
+    }
+
+    #[test]
+    fn test_du_report() {
+        let root = parse(include_str!("input-sample.txt"));
+        assert_eq!(
+            du_report(&root, None),
+            "   47M  /
+   24M  /d
+   93K  /a
+  584B  /a/e"
+        );
+        assert_eq!(du_report(&root, Some(0)), "   47M  /");
+        assert_eq!(du_report(&root, Some(1)).lines().count(), 3);
+    }
+}
@@ -0,0 +1,196 @@
+use crate::{Forrest, ScenicMap, VisibilityMap};
+
+pub type Rgb = [u8; 3];
+pub type ColourMap = Vec<Vec<Rgb>>;
+
+// the most scenic tree gets picked out in the same colour on every map
+const HIGHLIGHT: Rgb = [0, 160, 255];
+
+fn highlight(mut colours: ColourMap, best: Option<(usize, usize)>) -> ColourMap {
+    if let Some((x, y)) = best {
+        colours[y][x] = HIGHLIGHT;
+    }
+    colours
+}
+
+// shorter trees are darker. the tallest are a light grey rather than white, so that white is
+// left free for the highlight in the pgm
+pub fn height_colours(forrest: &Forrest, best: Option<(usize, usize)>) -> ColourMap {
+    let colours = forrest
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|&height| {
+                    let grey = (height as usize * 230 / 9) as u8;
+                    [grey, grey, grey]
+                })
+                .collect()
+        })
+        .collect();
+    highlight(colours, best)
+}
+
+// visible trees are green (brighter for taller ones), hidden ones are a dull grey
+pub fn visibility_colours(
+    forrest: &Forrest,
+    vis: &VisibilityMap,
+    best: Option<(usize, usize)>,
+) -> ColourMap {
+    let colours = forrest
+        .iter()
+        .zip(vis.iter())
+        .map(|(row, vis_row)| {
+            row.iter()
+                .zip(vis_row.iter())
+                .map(|(&height, &visible)| {
+                    let shade = height * 12;
+                    if visible {
+                        [0, 100 + shade, 0]
+                    } else {
+                        [40 + shade / 2, 40 + shade / 2, 40 + shade / 2]
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    highlight(colours, best)
+}
+
+// black through red and yellow to white as the score goes up.
+// most trees score very low next to the best one, so it's on a log scale to spread them out a bit
+pub fn scenic_colours(scores: &ScenicMap, best: Option<(usize, usize)>) -> ColourMap {
+    let max = (scores.iter().flatten().copied().max().unwrap_or(0) as f64).ln_1p();
+    let colours = scores
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|&score| {
+                    let t = if max > 0.0 {
+                        (score as f64).ln_1p() / max * 3.0
+                    } else {
+                        0.0
+                    };
+                    let channel = |offset: f64| ((t - offset).clamp(0.0, 1.0) * 255.0) as u8;
+                    [channel(0.0), channel(1.0), channel(2.0)]
+                })
+                .collect()
+        })
+        .collect();
+    highlight(colours, best)
+}
+
+// binary ppm (P6), with each tree drawn as a [scale] x [scale] square
+pub fn to_ppm(colours: &ColourMap, scale: usize) -> Vec<u8> {
+    let mut bytes = header("P6", colours, scale);
+    for row in colours.iter() {
+        for _ in 0..scale {
+            for colour in row.iter() {
+                for _ in 0..scale {
+                    bytes.extend_from_slice(colour);
+                }
+            }
+        }
+    }
+    bytes
+}
+
+// binary pgm (P5), colours are turned into greys by their luminance.
+// the highlight would come out a dull mid grey, so [best] is drawn in white instead
+pub fn to_pgm(colours: &ColourMap, best: Option<(usize, usize)>, scale: usize) -> Vec<u8> {
+    let mut bytes = header("P5", colours, scale);
+    for (y, row) in colours.iter().enumerate() {
+        for _ in 0..scale {
+            for (x, colour) in row.iter().enumerate() {
+                let grey = if best == Some((x, y)) {
+                    u8::MAX
+                } else {
+                    luminance(colour)
+                };
+                bytes.extend(std::iter::repeat_n(grey, scale));
+            }
+        }
+    }
+    bytes
+}
+
+fn header(magic: &str, colours: &ColourMap, scale: usize) -> Vec<u8> {
+    let width = colours.first().map_or(0, |row| row.len()) * scale;
+    let height = colours.len() * scale;
+    format!("{}\n{} {}\n255\n", magic, width, height).into_bytes()
+}
+
+fn luminance(&[r, g, b]: &Rgb) -> u8 {
+    ((r as usize * 299 + g as usize * 587 + b as usize * 114) / 1000) as u8
+}
+
+// each tree's height on a coloured background, using 24 bit ansi colours
+pub fn to_ansi(colours: &ColourMap, forrest: &Forrest) -> String {
+    let mut out = String::new();
+    for (row, heights) in colours.iter().zip(forrest.iter()) {
+        for (&[r, g, b], height) in row.iter().zip(heights.iter()) {
+            // dark text on light backgrounds so the digits stay readable
+            let fg = if luminance(&[r, g, b]) > 128 { 0 } else { 255 };
+            out.push_str(&format!(
+                "\x1b[48;2;{};{};{}m\x1b[38;2;{};{};{}m{}",
+                r, g, b, fg, fg, fg, height
+            ));
+        }
+        out.push_str("\x1b[0m\n");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_height_pgm() {
+        let forrest = vec![vec![0, 9, 3], vec![9, 0, 6]];
+        let pgm = to_pgm(&height_colours(&forrest, None), None, 1);
+        assert_eq!(pgm, b"P5\n3 2\n255\n\x00\xe6\x4c\xe6\x00\x99");
+        // the highlight stands out from the tallest trees
+        let pgm = to_pgm(&height_colours(&forrest, Some((1, 1))), Some((1, 1)), 1);
+        assert_eq!(pgm, b"P5\n3 2\n255\n\x00\xe6\x4c\xe6\xff\x99");
+        let pixels = &pgm[11..];
+        assert_ne!(pixels[4], pixels[1]);
+    }
+
+    #[test]
+    fn test_scaled_ppm() {
+        let forrest = vec![vec![9, 0]];
+        let ppm = to_ppm(&height_colours(&forrest, Some((1, 0))), 2);
+        let (header, pixels) = ppm.split_at(11);
+        assert_eq!(header, b"P6\n4 2\n255\n");
+        let row = [[230, 230, 230], [230, 230, 230], HIGHLIGHT, HIGHLIGHT].concat();
+        assert_eq!(pixels, [row.clone(), row].concat());
+    }
+
+    #[test]
+    fn test_visibility_colours() {
+        let forrest = vec![vec![1, 0, 1]];
+        let vis = vec![vec![true, false, true]];
+        let colours = visibility_colours(&forrest, &vis, None);
+        assert_eq!(colours, vec![vec![[0, 112, 0], [40, 40, 40], [0, 112, 0]]]);
+    }
+
+    #[test]
+    fn test_scenic_colours() {
+        let scores = vec![vec![0, 4, 0], vec![16, 0, 0]];
+        let colours = scenic_colours(&scores, None);
+        assert_eq!(colours[0][0], [0, 0, 0]);
+        assert_eq!(colours[0][1], [255, 179, 0]); // ln(5) / ln(17) is about 0.57
+        assert_eq!(colours[1][0], [255, 255, 255]);
+        assert_eq!(scenic_colours(&scores, Some((0, 1)))[1][0], HIGHLIGHT);
+    }
+
+    #[test]
+    fn test_ansi() {
+        let forrest = vec![vec![9, 0]];
+        let ansi = to_ansi(&height_colours(&forrest, None), &forrest);
+        assert_eq!(
+            ansi,
+            "\x1b[48;2;230;230;230m\x1b[38;2;0;0;0m9\x1b[48;2;0;0;0m\x1b[38;2;255;255;255m0\x1b[0m\n"
+        );
+    }
+}
@@ -1,7 +1,14 @@
+mod heatmap;
+mod query;
+mod sight;
+
+use sight::{look_towards, Direction, Rule, Sight};
+
 type Forrest = Vec<Vec<u8>>;
 type VisibilityMap = Vec<Vec<bool>>;
 type ScenicMap = Vec<Vec<usize>>;
 
+#[derive(Debug, Clone, Copy)]
 enum Side {
     Top,
     Bottom,
@@ -9,8 +16,6 @@ enum Side {
     Right,
 }
 
-use Side::*;
-
 fn parse(input: &str) -> Forrest {
     input
         .lines()
@@ -24,160 +29,74 @@ fn parse(input: &str) -> Forrest {
         .collect()
 }
 
-fn calculate_visibility_pass(forrest: &Forrest, side: Side) -> VisibilityMap {
-    let forrest_height = forrest.len();
-    let forrest_width = forrest.get(0).expect("there must be at leastone row").len();
-
-    // pre-fill the vec
-    let mut result: VisibilityMap = Vec::with_capacity(forrest_height);
-    for _ in 0..forrest_height {
-        result.push(vec![false; forrest_width]);
-    }
-
-    let outer = match side {
-        Left | Right => 0..forrest_height, // rows
-        Top | Bottom => 0..forrest_width,  // cols
-    };
-    for i in outer {
-        let mut max_height: i8 = -1;
-        let inner: Box<dyn Iterator<Item = usize>> = match side {
-            Left => Box::new(0..forrest_width),
-            Right => Box::new((0..forrest_width).rev()),
-            Top => Box::new(0..forrest_height),
-            Bottom => Box::new((0..forrest_height).rev()),
-        };
+// marks the trees that can be seen from outside the forrest looking back along [direction] in [result]
+fn calculate_visibility_pass(
+    forrest: &Forrest,
+    direction: Direction,
+    sight: &Sight,
+    result: &mut VisibilityMap,
+) {
+    look_towards(forrest, direction, sight, |x, y, view| {
+        result[y][x] = result[y][x] || view.to_edge;
+    });
+}
 
-        for j in inner {
-            let (x, y) = match side {
-                Left | Right => (j, i),
-                Top | Bottom => (i, j),
-            };
-            let height = forrest[y][x] as i8;
-            // print!(
-            //     "checking {} ({}, {}) against max_height {}: ",
-            //     height, x, y, max_height
-            // );
-            let visible = height > max_height;
-            if visible {
-                max_height = height;
-                // println!("visible");
-            } else {
-                // println!("hidden");
-            };
-            result[y][x] = visible;
-        }
+fn get_visibility(forrest: &Forrest, sight: &Sight) -> VisibilityMap {
+    let forrest_width = forrest
+        .first()
+        .expect("there must be at leastone row")
+        .len();
+    let mut result: VisibilityMap = vec![vec![false; forrest_width]; forrest.len()];
+    for direction in sight.directions.iter() {
+        calculate_visibility_pass(forrest, *direction, sight, &mut result);
     }
     result
 }
 
-fn get_visibility(forrest: &Forrest) -> VisibilityMap {
-    let vis_left = calculate_visibility_pass(&forrest, Left);
-    // println!("vis left {:?}", vis_left);
-    let vis_right = calculate_visibility_pass(&forrest, Right);
-    // println!("vis right {:?}", vis_right);
-    let vis_top = calculate_visibility_pass(&forrest, Top);
-    // println!("vis top {:?}", vis_top);
-    let vis_bottom = calculate_visibility_pass(&forrest, Bottom);
-    //println!("vis bottom {:?}", vis_bottom);
-
-    combine_visibility(
-        combine_visibility(vis_top, vis_bottom),
-        combine_visibility(vis_left, vis_right),
-    )
+fn count_visible(map: VisibilityMap) -> usize {
+    map.into_iter().flatten().filter(|&vis| vis).count()
 }
 
-fn count_visible(map: VisibilityMap) -> usize {
-    let mut count = 0;
-    for row in map.into_iter() {
-        for vis in row.into_iter() {
-            if vis {
-                count = count + 1;
-            }
-        }
-    }
-    count
+// multiplies each tree's score in [scores] by how far it can see along [direction]
+fn viewing_distance_pass(
+    forrest: &Forrest,
+    direction: Direction,
+    sight: &Sight,
+    scores: &mut ScenicMap,
+) {
+    look_towards(forrest, direction, sight, |x, y, view| {
+        scores[y][x] *= view.distance;
+    });
 }
 
-// there's probably some cool matrix/vector/avx way to make this really fast and simple..
-fn combine_visibility(a_map: VisibilityMap, b_map: VisibilityMap) -> VisibilityMap {
-    a_map
-        .into_iter()
-        .zip(b_map.into_iter())
-        .map(|(a_row, b_row)| {
-            a_row
-                .into_iter()
-                .zip(b_row.into_iter())
-                .map(|(a, b)| a || b)
-                .collect()
-        })
-        .collect()
+fn senic_scores(forrest: &Forrest, sight: &Sight) -> ScenicMap {
+    let forrest_width = forrest
+        .first()
+        .expect("there must be at leastone row")
+        .len();
+    let mut scores: ScenicMap = vec![vec![1; forrest_width]; forrest.len()];
+    for direction in sight.directions.iter() {
+        viewing_distance_pass(forrest, *direction, sight, &mut scores);
+    }
+    scores
 }
 
-fn senic_scores(forrest: &Forrest) -> ScenicMap {
-    let forrest_height = forrest.len();
-    let forrest_width = forrest.get(0).expect("there must be at leastone row").len();
-    // there's definately room for some optimization in here
-    forrest
-        .into_iter()
-        .enumerate()
-        .map(|(y, row)| {
-            row.into_iter()
-                .enumerate()
-                .map(|(x, height)| {
-                    let mut left = 0;
-                    for x2 in (0..x).rev() {
-                        assert_ne!(x, x2);
-                        left = left + 1;
-                        let side_tree_height = forrest[y][x2];
-                        if side_tree_height >= *height {
-                            break;
-                        }
-                    }
-                    let mut right = 0;
-                    for x2 in (x + 1)..forrest_width {
-                        assert_ne!(x, x2);
-                        right = right + 1;
-                        let side_tree_height = forrest[y][x2];
-                        if side_tree_height >= *height {
-                            break;
-                        }
-                    }
-
-                    let mut top = 0;
-                    for y2 in (0..y).rev() {
-                        assert_ne!(y, y2);
-                        top = top + 1;
-                        let side_tree_height = forrest[y2][x];
-                        if side_tree_height >= *height {
-                            break;
-                        }
-                    }
-
-                    let mut bottom = 0;
-                    for y2 in (y + 1)..forrest_height {
-                        assert_ne!(y, y2);
-                        bottom = bottom + 1;
-                        let side_tree_height = forrest[y2][x];
-                        if side_tree_height >= *height {
-                            break;
-                        }
-                    }
-                    // println!(
-                    //     "scpore for ({}, {}) with height {} is {}*{}*{}*{}={}",
-                    //     x,
-                    //     y,
-                    //     height,
-                    //     top,
-                    //     left,
-                    //     right,
-                    //     bottom,
-                    //     top * bottom * left * right
-                    // );
-                    top * bottom * left * right
-                })
-                .collect()
-        })
-        .collect()
+// reads `--diagonals`, `--same-height-ok` and `--max-distance N` from the command line
+fn sight_from_args(args: &[String]) -> Sight {
+    let mut sight = Sight::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--diagonals" => sight = sight.with_diagonals(),
+            "--same-height-ok" => sight.rule = Rule::AtLeastAsTall,
+            "--max-distance" => {
+                let distance = args.next().expect("--max-distance needs a number");
+                sight.max_distance = Some(distance.parse().expect("unparseable max distance"));
+            }
+            _ => {}
+        }
+    }
+    sight
 }
 
 fn most_scenic(map: ScenicMap) -> usize {
@@ -186,14 +105,90 @@ fn most_scenic(map: ScenicMap) -> usize {
         .max()
         .unwrap()
 }
+
+// the (x, y) of the tree with the best score, the first one if there's a tie
+fn most_scenic_tree(map: &ScenicMap) -> Option<(usize, usize)> {
+    let mut best: Option<(usize, usize, usize)> = None;
+    for (y, row) in map.iter().enumerate() {
+        for (x, &score) in row.iter().enumerate() {
+            if best.is_none_or(|(_, _, best_score)| score > best_score) {
+                best = Some((x, y, score));
+            }
+        }
+    }
+    best.map(|(x, y, _)| (x, y))
+}
+
 fn main() {
     let input = include_str!("input.txt");
     let forrest = parse(input);
+
+    // `cargo run -- show <heights | visibility | scenic>` draws a map in the terminal,
+    // `cargo run -- export <dir> [scale]` saves them all as images.
+    // `cargo run -- tree <x> <y>` says what one tree can see, `cargo run -- top <k>` lists the most scenic trees.
+    // add `--diagonals`, `--same-height-ok` or `--max-distance N` to change how far the trees can see
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(command @ ("show" | "export")) = args.first().map(String::as_str) {
+        let sight = sight_from_args(&args);
+        let vis = get_visibility(&forrest, &sight);
+        let scores = senic_scores(&forrest, &sight);
+        let best = most_scenic_tree(&scores);
+        let heights = heatmap::height_colours(&forrest, best);
+        let visibility = heatmap::visibility_colours(&forrest, &vis, best);
+        let scenic = heatmap::scenic_colours(&scores, best);
+        if command == "show" {
+            let colours = match args.get(1).map(String::as_str) {
+                Some("heights") => heights,
+                Some("visibility") => visibility,
+                _ => scenic,
+            };
+            print!("{}", heatmap::to_ansi(&colours, &forrest));
+        } else {
+            let dir = std::path::Path::new(args.get(1).map_or(".", String::as_str));
+            let scale = args
+                .get(2)
+                .map_or(4, |scale| scale.parse().expect("scale must be a number"));
+            let files = [
+                ("heights.pgm", heatmap::to_pgm(&heights, best, scale)),
+                ("visibility.ppm", heatmap::to_ppm(&visibility, scale)),
+                ("scenic.ppm", heatmap::to_ppm(&scenic, scale)),
+            ];
+            for (name, bytes) in files {
+                std::fs::write(dir.join(name), bytes).expect("failed to write image");
+                println!("wrote {}", dir.join(name).display());
+            }
+        }
+        return;
+    }
+
+    if let Some(command @ ("tree" | "top")) = args.first().map(String::as_str) {
+        let sight = sight_from_args(&args);
+        let survey = query::Survey::new(&forrest, &sight);
+        let number = |i: usize| -> usize {
+            args.get(i)
+                .and_then(|arg| arg.parse().ok())
+                .unwrap_or_else(|| panic!("`{}` needs a number in position {}", command, i))
+        };
+        if command == "tree" {
+            let (x, y) = (number(1), number(2));
+            match survey.tree(x, y) {
+                Some(tree) => println!("{}", tree),
+                None => println!("there's no tree at ({}, {})", x, y),
+            }
+        } else {
+            for ((x, y), score) in survey.top_scenic(number(1)) {
+                println!("({}, {}): {}", x, y, score);
+            }
+        }
+        return;
+    }
+
     //println!("parsed: {:?}", parse(input));
-    let vis = get_visibility(&forrest);
+    let sight = sight_from_args(&args);
+    let vis = get_visibility(&forrest, &sight);
     println!("num visible: {}", count_visible(vis));
 
-    let scores = senic_scores(&forrest);
+    let scores = senic_scores(&forrest, &sight);
     //println!("scenic scores: {:?}", scores);
     println!("best scenic score: {}", most_scenic(scores));
 }
@@ -201,6 +196,88 @@ fn main() {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use Side::*;
+
+    fn single_pass(forrest: &Forrest, side: Side) -> VisibilityMap {
+        let mut vis = vec![vec![false; forrest[0].len()]; forrest.len()];
+        calculate_visibility_pass(forrest, side.into(), &Sight::default(), &mut vis);
+        vis
+    }
+
+    // the original version, walks outwards from every tree, kept around to check the fast one against
+    #[allow(clippy::needless_range_loop)]
+    fn senic_scores_naive(forrest: &Forrest) -> ScenicMap {
+        let forrest_height = forrest.len();
+        let forrest_width = forrest
+            .first()
+            .expect("there must be at leastone row")
+            .len();
+        forrest
+            .iter()
+            .enumerate()
+            .map(|(y, row)| {
+                row.iter()
+                    .enumerate()
+                    .map(|(x, height)| {
+                        let mut left = 0;
+                        for x2 in (0..x).rev() {
+                            left += 1;
+                            let side_tree_height = forrest[y][x2];
+                            if side_tree_height >= *height {
+                                break;
+                            }
+                        }
+                        let mut right = 0;
+                        for x2 in (x + 1)..forrest_width {
+                            right += 1;
+                            let side_tree_height = forrest[y][x2];
+                            if side_tree_height >= *height {
+                                break;
+                            }
+                        }
+
+                        let mut top = 0;
+                        for y2 in (0..y).rev() {
+                            top += 1;
+                            let side_tree_height = forrest[y2][x];
+                            if side_tree_height >= *height {
+                                break;
+                            }
+                        }
+
+                        let mut bottom = 0;
+                        for y2 in (y + 1)..forrest_height {
+                            bottom += 1;
+                            let side_tree_height = forrest[y2][x];
+                            if side_tree_height >= *height {
+                                break;
+                            }
+                        }
+                        top * bottom * left * right
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    // xorshift, just enough randomness to generate test forests without pulling in a crate
+    fn next_random(state: &mut u64) -> u64 {
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        *state
+    }
+
+    fn random_forrest(seed: u64, width: usize, height: usize, max_height: u64) -> Forrest {
+        let mut state = seed;
+        (0..height)
+            .map(|_| {
+                (0..width)
+                    .map(|_| (next_random(&mut state) % (max_height + 1)) as u8)
+                    .collect()
+            })
+            .collect()
+    }
 
     #[test]
     fn test_parse() {
@@ -222,7 +299,7 @@ mod tests {
         789";
         let forrest = parse(input);
         assert_eq!(
-            calculate_visibility_pass(&forrest, Top),
+            single_pass(&forrest, Top),
             vec![
                 vec![true, true, true],
                 vec![true, false, true],
@@ -239,7 +316,7 @@ mod tests {
         789";
         let forrest = parse(input);
         assert_eq!(
-            calculate_visibility_pass(&forrest, Bottom),
+            single_pass(&forrest, Bottom),
             vec![
                 vec![false, false, false],
                 vec![false, false, false],
@@ -255,7 +332,7 @@ mod tests {
         789";
         let forrest = parse(input);
         assert_eq!(
-            calculate_visibility_pass(&forrest, Left),
+            single_pass(&forrest, Left),
             vec![
                 vec![true, true, true],
                 vec![true, true, true],
@@ -271,7 +348,7 @@ mod tests {
         789";
         let forrest = parse(input);
         assert_eq!(
-            calculate_visibility_pass(&forrest, Right),
+            single_pass(&forrest, Right),
             vec![
                 vec![false, false, true],
                 vec![false, false, true],
@@ -281,23 +358,22 @@ mod tests {
     }
 
     #[test]
-    fn test_combine_visibility() {
-        let a = vec![
-            vec![true, true, true],
-            vec![false, false, false],
-            vec![false, false, false],
-        ];
-        let b = vec![
-            vec![true, false, false],
-            vec![true, false, false],
-            vec![true, false, false],
-        ];
+    fn test_passes_accumulate() {
+        let input = "
+        123
+        456
+        789";
+        let forrest = parse(input);
+        let mut vis = vec![vec![false; 3]; 3];
+        let sight = Sight::default();
+        calculate_visibility_pass(&forrest, Bottom.into(), &sight, &mut vis);
+        calculate_visibility_pass(&forrest, Right.into(), &sight, &mut vis);
         assert_eq!(
-            combine_visibility(a, b),
+            vis,
             vec![
-                vec![true, true, true],
-                vec![true, false, false],
-                vec![true, false, false]
+                vec![false, false, true],
+                vec![false, false, true],
+                vec![true, true, true]
             ]
         );
     }
@@ -306,7 +382,7 @@ mod tests {
     fn test_sample_input_count() {
         let input = include_str!("input-sample.txt");
         let forrest = parse(input);
-        let vis = get_visibility(&forrest);
+        let vis = get_visibility(&forrest, &Sight::default());
         assert_eq!(count_visible(vis), 21);
     }
 
@@ -314,7 +390,60 @@ mod tests {
     fn test_sample_input_most_scenic() {
         let input = include_str!("input-sample.txt");
         let forrest = parse(input);
-        let scores = senic_scores(&forrest);
+        let scores = senic_scores(&forrest, &Sight::default());
+        assert_eq!(most_scenic_tree(&scores), Some((2, 3)));
         assert_eq!(most_scenic(scores), 8);
     }
+
+    #[test]
+    fn test_scenic_scores_against_naive() {
+        for seed in 1..200 {
+            let mut state = seed;
+            let width = 1 + (next_random(&mut state) % 12) as usize;
+            let height = 1 + (next_random(&mut state) % 12) as usize;
+            let max_height = next_random(&mut state) % 10;
+            let forrest = random_forrest(seed, width, height, max_height);
+            assert_eq!(
+                senic_scores(&forrest, &Sight::default()),
+                senic_scores_naive(&forrest),
+                "forrest: {:?}",
+                forrest
+            );
+        }
+    }
+
+    #[test]
+    fn test_sample_scenic_scores() {
+        let forrest = parse(include_str!("input-sample.txt"));
+        let scores = senic_scores(&forrest, &Sight::default());
+        assert_eq!(scores[1][2], 4);
+        assert_eq!(scores[3][2], 8);
+        assert_eq!(scores, senic_scores_naive(&forrest));
+    }
+
+    #[test]
+    fn test_sample_with_diagonals() {
+        let forrest = parse(include_str!("input-sample.txt"));
+        let sight = Sight::default().with_diagonals();
+        // the 4 in the fourth row is hidden straight on but can see out diagonally
+        assert!(!get_visibility(&forrest, &Sight::default())[3][3]);
+        assert!(get_visibility(&forrest, &sight)[3][3]);
+        let scores = senic_scores(&forrest, &sight);
+        assert_eq!(scores[0][0], 0);
+        // 4 straight on (same as the puzzle), then 1 up-left, 1 up-right, 1 down-left and 2 down-right
+        assert_eq!(scores[1][2], 8);
+        assert_eq!(scores[3][3], 6);
+    }
+
+    #[test]
+    fn test_sight_from_args() {
+        let args: Vec<String> = ["show", "--diagonals", "--max-distance", "3"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        let sight = sight_from_args(&args);
+        assert_eq!(sight.directions.len(), 8);
+        assert_eq!(sight.rule, Rule::Taller);
+        assert_eq!(sight.max_distance, Some(3));
+    }
 }
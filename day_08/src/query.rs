@@ -0,0 +1,181 @@
+use std::fmt;
+
+use crate::sight::{look_towards, Direction, Sight, View};
+use crate::Forrest;
+
+// everything a single tree can see, one view for each of the sight's directions
+#[derive(Debug, PartialEq)]
+pub struct Tree {
+    pub x: usize,
+    pub y: usize,
+    pub height: u8,
+    pub views: Vec<(Direction, View)>,
+}
+
+impl Tree {
+    // the directions it can be seen from outside the forrest
+    pub fn visible_from(&self) -> Vec<Direction> {
+        self.views
+            .iter()
+            .filter(|(_, view)| view.to_edge)
+            .map(|&(direction, _)| direction)
+            .collect()
+    }
+
+    pub fn score(&self) -> usize {
+        self.views.iter().map(|(_, view)| view.distance).product()
+    }
+}
+
+impl fmt::Display for Tree {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "tree at ({}, {}), height {}",
+            self.x, self.y, self.height
+        )?;
+        let visible_from: Vec<String> = self.visible_from().iter().map(|d| d.to_string()).collect();
+        if visible_from.is_empty() {
+            writeln!(f, "  hidden from every side")?;
+        } else {
+            writeln!(f, "  visible from {}", visible_from.join(", "))?;
+        }
+        for (direction, view) in self.views.iter() {
+            write!(f, "  {:<10} sees {}", direction.to_string(), view.distance)?;
+            match view.blocker {
+                Some((x, y)) => writeln!(f, ", blocked by ({}, {})", x, y)?,
+                None if view.to_edge => writeln!(f, " to the edge")?,
+                None => writeln!(f, ", too far to see the edge")?,
+            }
+        }
+        write!(f, "  scenic score {}", self.score())
+    }
+}
+
+// looks in every direction once up front, so any number of questions about single trees
+// can be answered afterwards without walking the forrest again
+pub struct Survey<'a> {
+    forrest: &'a Forrest,
+    directions: Vec<Direction>,
+    views: Vec<Vec<Vec<View>>>, // [y][x][direction]
+}
+
+impl<'a> Survey<'a> {
+    pub fn new(forrest: &'a Forrest, sight: &Sight) -> Self {
+        let mut views = forrest
+            .iter()
+            .map(|row| vec![Vec::with_capacity(sight.directions.len()); row.len()])
+            .collect::<Vec<Vec<Vec<View>>>>();
+        for &direction in sight.directions.iter() {
+            look_towards(forrest, direction, sight, |x, y, view| {
+                views[y][x].push(view)
+            });
+        }
+        Survey {
+            forrest,
+            directions: sight.directions.clone(),
+            views,
+        }
+    }
+
+    // None if (x, y) is outside the forrest
+    pub fn tree(&self, x: usize, y: usize) -> Option<Tree> {
+        let height = *self.forrest.get(y)?.get(x)?;
+        Some(Tree {
+            x,
+            y,
+            height,
+            views: self
+                .directions
+                .iter()
+                .copied()
+                .zip(self.views[y][x].iter().copied())
+                .collect(),
+        })
+    }
+
+    // the [k] best scoring trees as ((x, y), score), best first. ties go in reading order
+    pub fn top_scenic(&self, k: usize) -> Vec<((usize, usize), usize)> {
+        let mut scores: Vec<((usize, usize), usize)> = self
+            .views
+            .iter()
+            .enumerate()
+            .flat_map(|(y, row)| {
+                row.iter().enumerate().map(move |(x, views)| {
+                    ((x, y), views.iter().map(|view| view.distance).product())
+                })
+            })
+            .collect();
+        scores.sort_by(|((a_x, a_y), a), ((b_x, b_y), b)| {
+            b.cmp(a).then_with(|| (a_y, a_x).cmp(&(b_y, b_x)))
+        });
+        scores.truncate(k);
+        scores
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, senic_scores, Side};
+
+    #[test]
+    fn test_sample_tree() {
+        let forrest = parse(include_str!("input-sample.txt"));
+        let survey = Survey::new(&forrest, &Sight::default());
+        // the middle 5 in the fourth row, from the puzzle description
+        let tree = survey.tree(2, 3).unwrap();
+        assert_eq!(tree.height, 5);
+        assert_eq!(tree.score(), 8);
+        let distances: Vec<usize> = tree.views.iter().map(|(_, view)| view.distance).collect();
+        assert_eq!(distances, vec![2, 2, 2, 1]); // left, right, up, down
+        assert_eq!(tree.views[0].1.blocker, None);
+        assert_eq!(tree.views[1].1.blocker, Some((4, 3)));
+        assert_eq!(tree.views[2].1.blocker, Some((2, 1)));
+        assert_eq!(tree.views[3].1.blocker, None);
+        assert_eq!(
+            tree.visible_from(),
+            vec![Direction::from(Side::Left), Direction::from(Side::Bottom)]
+        );
+    }
+
+    #[test]
+    fn test_hidden_tree() {
+        let forrest = parse(include_str!("input-sample.txt"));
+        let survey = Survey::new(&forrest, &Sight::default());
+        // the 1 in the middle of the forrest can't be seen from anywhere
+        assert_eq!(survey.tree(3, 1).unwrap().visible_from(), vec![]);
+        assert_eq!(survey.tree(5, 0), None);
+    }
+
+    #[test]
+    fn test_top_scenic() {
+        let forrest = parse(include_str!("input-sample.txt"));
+        let survey = Survey::new(&forrest, &Sight::default());
+        let top = survey.top_scenic(3);
+        assert_eq!(top, vec![((2, 3), 8), ((1, 2), 6), ((2, 1), 4)]);
+        // matches the full map of scores
+        let scores = senic_scores(&forrest, &Sight::default());
+        for ((x, y), score) in survey.top_scenic(25) {
+            assert_eq!(scores[y][x], score);
+        }
+        assert_eq!(survey.top_scenic(100).len(), 25);
+    }
+
+    #[test]
+    fn test_display() {
+        let forrest = parse("3125");
+        let survey = Survey::new(&forrest, &Sight::default());
+        let shown = survey.tree(2, 0).unwrap().to_string();
+        assert_eq!(
+            shown,
+            "tree at (2, 0), height 2
+  visible from up, down
+  left       sees 2, blocked by (0, 0)
+  right      sees 1, blocked by (3, 0)
+  up         sees 0 to the edge
+  down       sees 0 to the edge
+  scenic score 0"
+        );
+    }
+}
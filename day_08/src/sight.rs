@@ -0,0 +1,274 @@
+use crate::{Forrest, Side};
+
+// a direction to look in, as a step in x and y (y goes down the rows, so Top is dy = -1)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Direction {
+    pub dx: isize,
+    pub dy: isize,
+}
+
+impl From<Side> for Direction {
+    fn from(side: Side) -> Self {
+        match side {
+            Side::Top => Direction { dx: 0, dy: -1 },
+            Side::Bottom => Direction { dx: 0, dy: 1 },
+            Side::Left => Direction { dx: -1, dy: 0 },
+            Side::Right => Direction { dx: 1, dy: 0 },
+        }
+    }
+}
+
+impl std::fmt::Display for Direction {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let vertical = match self.dy {
+            0 => "",
+            dy if dy < 0 => "up",
+            _ => "down",
+        };
+        let horizontal = match self.dx {
+            0 => "",
+            dx if dx < 0 => "left",
+            _ => "right",
+        };
+        match (vertical, horizontal) {
+            ("", _) => write!(f, "{}", horizontal),
+            (_, "") => write!(f, "{}", vertical),
+            _ => write!(f, "{}-{}", vertical, horizontal),
+        }
+    }
+}
+
+pub const DIAGONALS: [Direction; 4] = [
+    Direction { dx: -1, dy: -1 },
+    Direction { dx: 1, dy: -1 },
+    Direction { dx: -1, dy: 1 },
+    Direction { dx: 1, dy: 1 },
+];
+
+// what it takes for a tree to see over the ones in front of it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rule {
+    Taller,        // has to be strictly taller, which is what the puzzle uses
+    AtLeastAsTall, // trees the same height don't get in the way
+}
+
+// which ways trees look, what blocks them, and how far they can see
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sight {
+    pub directions: Vec<Direction>,
+    pub rule: Rule,
+    pub max_distance: Option<usize>,
+}
+
+impl Default for Sight {
+    fn default() -> Self {
+        Sight {
+            directions: [Side::Left, Side::Right, Side::Top, Side::Bottom]
+                .map(Direction::from)
+                .to_vec(),
+            rule: Rule::Taller,
+            max_distance: None,
+        }
+    }
+}
+
+impl Sight {
+    pub fn with_diagonals(mut self) -> Self {
+        self.directions.extend(DIAGONALS);
+        self
+    }
+
+    fn blocks(&self, other: u8, height: u8) -> bool {
+        match self.rule {
+            Rule::Taller => other >= height,
+            Rule::AtLeastAsTall => other > height,
+        }
+    }
+}
+
+// what a single tree can see looking one way
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct View {
+    pub distance: usize,                 // how many trees it can see
+    pub blocker: Option<(usize, usize)>, // the (x, y) of the first tree that blocks the view
+    pub to_edge: bool,                   // whether it can see out past the edge
+}
+
+// walks each line of trees through the forrest starting from the edge that [direction] points at,
+// keeping a stack of the trees that could still block the view of the ones that come after them.
+// a tree pops everything that can't block it, so whatever is left on top is the first tree that does.
+// every tree is pushed and popped at most once, so it's linear in the number of trees.
+// calls [f] with each tree's coords and what it can see towards [direction].
+// with a max distance the view is cut short, and trees further than that from the edge can't be seen from outside
+pub fn look_towards(
+    forrest: &Forrest,
+    direction: Direction,
+    sight: &Sight,
+    mut f: impl FnMut(usize, usize, View),
+) {
+    let Direction { dx, dy } = direction;
+    assert!(dx != 0 || dy != 0, "direction can't be (0, 0)");
+    let forrest_height = forrest.len() as isize;
+    let forrest_width = forrest
+        .first()
+        .expect("there must be at leastone row")
+        .len() as isize;
+    let in_bounds =
+        |x: isize, y: isize| x >= 0 && y >= 0 && x < forrest_width && y < forrest_height;
+    let max_distance = sight.max_distance.unwrap_or(usize::MAX);
+
+    let mut stack: Vec<(usize, u8, (usize, usize))> = Vec::new(); // (position along the line, height, coords)
+    for start_y in 0..forrest_height {
+        for start_x in 0..forrest_width {
+            // every line starts at a tree with nothing in front of it
+            if in_bounds(start_x + dx, start_y + dy) {
+                continue;
+            }
+            stack.clear();
+            let (mut x, mut y) = (start_x, start_y);
+            let mut pos = 0;
+            while in_bounds(x, y) {
+                let height = forrest[y as usize][x as usize];
+                while stack
+                    .last()
+                    .is_some_and(|&(_, other, _)| !sight.blocks(other, height))
+                {
+                    stack.pop();
+                }
+                let coords = (x as usize, y as usize);
+                let view = match stack.last() {
+                    // a blocker further away than the tree can see doesn't count as what stopped it
+                    Some(&(blocker_pos, _, blocker)) => View {
+                        distance: (pos - blocker_pos).min(max_distance),
+                        blocker: (pos - blocker_pos <= max_distance).then_some(blocker),
+                        to_edge: false,
+                    },
+                    None => View {
+                        distance: pos.min(max_distance),
+                        blocker: None,
+                        to_edge: pos < max_distance,
+                    },
+                };
+                f(coords.0, coords.1, view);
+                stack.push((pos, height, coords));
+                x -= dx;
+                y -= dy;
+                pos += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    fn distances(forrest: &Forrest, direction: Direction, sight: &Sight) -> Vec<Vec<usize>> {
+        let mut result = vec![vec![0; forrest[0].len()]; forrest.len()];
+        look_towards(forrest, direction, sight, |x, y, view| {
+            result[y][x] = view.distance
+        });
+        result
+    }
+
+    #[test]
+    fn test_diagonal() {
+        let forrest = parse(
+            "
+            111
+            121
+            113",
+        );
+        let up_left = Direction { dx: -1, dy: -1 };
+        assert_eq!(
+            distances(&forrest, up_left, &Sight::default()),
+            vec![vec![0, 0, 0], vec![0, 1, 1], vec![0, 1, 2]]
+        );
+    }
+
+    #[test]
+    fn test_blocker() {
+        let forrest = parse("3125");
+        let mut views = Vec::new();
+        look_towards(
+            &forrest,
+            Side::Left.into(),
+            &Sight::default(),
+            |_, _, view| views.push(view),
+        );
+        assert_eq!(views[2].blocker, Some((0, 0)));
+        assert_eq!(views[2].distance, 2);
+        assert_eq!(
+            views[3],
+            View {
+                distance: 3,
+                blocker: None,
+                to_edge: true
+            }
+        );
+    }
+
+    #[test]
+    fn test_rule() {
+        let forrest = parse("2212");
+        let right = Direction::from(Side::Right);
+        assert_eq!(
+            distances(&forrest, right, &Sight::default()),
+            vec![vec![1, 2, 1, 0]]
+        );
+        let sight = Sight {
+            rule: Rule::AtLeastAsTall,
+            ..Sight::default()
+        };
+        assert_eq!(distances(&forrest, right, &sight), vec![vec![3, 2, 1, 0]]);
+    }
+
+    #[test]
+    fn test_max_distance() {
+        let forrest = parse("0000");
+        let left = Direction::from(Side::Left);
+        let sight = Sight {
+            rule: Rule::AtLeastAsTall,
+            max_distance: Some(2),
+            ..Sight::default()
+        };
+        let mut seen = Vec::new();
+        look_towards(&forrest, left, &sight, |x, _, view| {
+            seen.push((x, view.distance, view.to_edge))
+        });
+        assert_eq!(
+            seen,
+            vec![(0, 0, true), (1, 1, true), (2, 2, false), (3, 2, false)]
+        );
+    }
+
+    #[test]
+    fn test_blocker_out_of_reach() {
+        let forrest = parse("90001");
+        let sight = Sight {
+            max_distance: Some(2),
+            ..Sight::default()
+        };
+        let mut blockers = Vec::new();
+        look_towards(&forrest, Side::Left.into(), &sight, |_, _, view| {
+            blockers.push(view.blocker)
+        });
+        // the 1 at the end can only see two trees, and the 9 is four away
+        assert_eq!(
+            blockers,
+            vec![None, Some((0, 0)), Some((1, 0)), Some((2, 0)), None]
+        );
+    }
+
+    #[test]
+    fn test_every_tree_is_visited_once() {
+        let forrest = parse(include_str!("input-sample.txt"));
+        let sight = Sight::default().with_diagonals();
+        for direction in sight.directions.iter() {
+            let mut visits = vec![vec![0; 5]; 5];
+            look_towards(&forrest, *direction, &sight, |x, y, _| visits[y][x] += 1);
+            assert_eq!(visits, vec![vec![1; 5]; 5], "{:?}", direction);
+        }
+    }
+}
@@ -1,112 +1,336 @@
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 enum Dir {
     Right,
     Left,
     Up,
     Down,
+    UpRight,
+    UpLeft,
+    DownRight,
+    DownLeft,
 }
 
-use std::{cmp::Ordering, collections::HashSet};
+mod render;
+mod rope;
 
+use std::{
+    fmt,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use render::Every;
+use rope::Rope;
 use Dir::*;
 
-#[derive(Debug)]
-struct Move {
-    dir: Dir,
-    dist: u8,
+impl Dir {
+    // how far one step goes in x and y, y goes up
+    fn offset(self) -> (i64, i64) {
+        match self {
+            Right => (1, 0),
+            Left => (-1, 0),
+            Up => (0, 1),
+            Down => (0, -1),
+            UpRight => (1, 1),
+            UpLeft => (-1, 1),
+            DownRight => (1, -1),
+            DownLeft => (-1, -1),
+        }
+    }
+
+    // the step that gets closer to somewhere [dx], [dy] away, None if it's already there
+    fn towards(dx: i64, dy: i64) -> Option<Dir> {
+        match (dx.signum(), dy.signum()) {
+            (1, 0) => Some(Right),
+            (-1, 0) => Some(Left),
+            (0, 1) => Some(Up),
+            (0, -1) => Some(Down),
+            (1, 1) => Some(UpRight),
+            (-1, 1) => Some(UpLeft),
+            (1, -1) => Some(DownRight),
+            (-1, -1) => Some(DownLeft),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Right => "R",
+            Left => "L",
+            Up => "U",
+            Down => "D",
+            UpRight => "UR",
+            UpLeft => "UL",
+            DownRight => "DR",
+            DownLeft => "DL",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Move {
+    Step { dir: Dir, dist: u32 }, // "R 4" or "UL 4"
+    By { dx: i32, dy: i32 },      // "by 3 -2" moves the head 3 right and 2 down
+    To(Loc),                      // "to 3 -2" moves the head to (3, -2)
+}
+
+impl Move {
+    // the straight runs the head takes to carry out the move starting from [head].
+    // moving to a point goes diagonally until it's lined up, then straight the rest of the way
+    fn legs(&self, head: Loc) -> Vec<(Dir, u64)> {
+        let (dx, dy) = match *self {
+            Move::Step { dir, dist } => return vec![(dir, dist.into())],
+            Move::By { dx, dy } => (dx.into(), dy.into()),
+            Move::To(to) => (to.x - head.x, to.y - head.y),
+        };
+        let diagonal = dx.abs().min(dy.abs());
+        let (straight_dx, straight_dy) = (dx - dx.signum() * diagonal, dy - dy.signum() * diagonal);
+        let straight = straight_dx.abs().max(straight_dy.abs());
+        [
+            (Dir::towards(dx, dy), diagonal),
+            (Dir::towards(straight_dx, straight_dy), straight),
+        ]
+        .into_iter()
+        .filter(|&(_, dist)| dist > 0)
+        .map(|(dir, dist)| (dir.expect("only moving legs are kept"), dist as u64))
+        .collect()
+    }
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Move::Step { dir, dist } => write!(f, "{} {}", dir.name(), dist),
+            Move::By { dx, dy } => write!(f, "by {} {}", dx, dy),
+            Move::To(Loc { x, y }) => write!(f, "to {} {}", x, y),
+        }
+    }
 }
 
 #[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
+// the input only goes up to i32, but positions are i64 so that moving around can't overflow them
 struct Loc {
-    x: i32,
-    y: i32,
+    x: i64,
+    y: i64,
 }
 
 fn parse(input: &str) -> Vec<Move> {
     input
         .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
         .map(|line| {
-            let (dir_str, dist_str) = line.trim().split_once(" ").expect("no whitespace inline");
-            let dir = match dir_str {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            let coord = |i: usize| -> i32 {
+                parts
+                    .get(i)
+                    .and_then(|part| part.parse().ok())
+                    .unwrap_or_else(|| panic!("expected two coordinates in `{}`", line))
+            };
+            let dir = match parts[0] {
+                "to" => {
+                    return Move::To(Loc {
+                        x: coord(1).into(),
+                        y: coord(2).into(),
+                    })
+                }
+                "by" => {
+                    return Move::By {
+                        dx: coord(1),
+                        dy: coord(2),
+                    }
+                }
                 "U" => Up,
                 "D" => Down,
                 "L" => Left,
                 "R" => Right,
-                _ => panic!("unexpected direction input"),
+                "UR" => UpRight,
+                "UL" => UpLeft,
+                "DR" => DownRight,
+                "DL" => DownLeft,
+                _ => panic!("unexpected direction input in `{}`", line),
             };
-            let dist = dist_str.parse().expect("unparseable distance");
-            Move { dir, dist }
+            // same as coordinates, a move can go as far as i32::MAX
+            let dist = parts
+                .get(1)
+                .expect("no whitespace inline")
+                .parse::<i32>()
+                .ok()
+                .and_then(|dist| u32::try_from(dist).ok())
+                .unwrap_or_else(|| panic!("unparseable distance in `{}`", line));
+            Move::Step { dir, dist }
         })
         .collect()
 }
 
 fn step_h(h: Loc, dir: Dir) -> Loc {
-    match dir {
-        Up => Loc { x: h.x, y: h.y + 1 },
-        Down => Loc { x: h.x, y: h.y - 1 },
-        Right => Loc { x: h.x + 1, y: h.y },
-        Left => Loc { x: h.x - 1, y: h.y },
+    let (dx, dy) = dir.offset();
+    Loc {
+        x: h.x + dx,
+        y: h.y + dy,
     }
 }
 
+// a knot that isn't touching the one in front moves one step straight or diagonally towards it.
+// the head can now move diagonally too, so the one in front can be up to two away in both x and y
 fn step_t(t: Loc, h: Loc) -> Loc {
     let dx = h.x - t.x;
     let dy = h.y - t.y;
-    let dxa = dx.abs();
-    let dya = dy.abs();
-    if dxa <= 1 && dya <= 1 {
+    if dx.abs() <= 1 && dy.abs() <= 1 {
         return t;
     }
-    let x = match dx.cmp(&0) {
-        Ordering::Greater => t.x + 1,
-        Ordering::Equal => t.x,
-        Ordering::Less => t.x - 1,
-    };
-    let y = match dy.cmp(&0) {
-        Ordering::Greater => t.y + 1,
-        Ordering::Equal => t.y,
-        Ordering::Less => t.y - 1,
-    };
-    Loc { x, y }
+    Loc {
+        x: t.x + dx.signum(),
+        y: t.y + dy.signum(),
+    }
 }
 
-fn follow(moves: Vec<Move>, num_knots: usize) -> usize {
-    let mut h = Loc { x: 0, y: 0 };
-    let mut knots: Vec<Loc> = Vec::with_capacity(num_knots);
-    for _ in 0..num_knots {
-        let t = Loc { x: 0, y: 0 };
-        knots.push(t);
-    }
-    let mut tail_locations = HashSet::from([knots[num_knots - 1]]);
-    for m in moves {
-        //println!("move: {:?}", m);
-        for _ in 0..(m.dist) {
-            h = step_h(h, m.dir);
-            let knot = step_t(knots[0], h);
-            knots[0] = knot;
-            for i in 1..num_knots {
-                let knot = step_t(knots[i], knots[i - 1]);
-                knots[i] = knot;
-            }
-            tail_locations.insert(knots[num_knots - 1]);
+// xorshift, just enough randomness to generate test moves without pulling in a crate
+fn next_random(state: &mut u64) -> u64 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    *state
+}
+
+// [count] random moves of every kind, straight and diagonal ones go up to [max_dist].
+// the same [seed] always gives the same moves
+fn generate_moves(seed: u64, count: usize, max_dist: u32) -> Vec<Move> {
+    let dirs = [Right, Left, Up, Down, UpRight, UpLeft, DownRight, DownLeft];
+    let mut state = seed.max(1);
+    let coord =
+        |state: &mut u64| (next_random(state) % (2 * max_dist as u64 + 1)) as i32 - max_dist as i32;
+    (0..count)
+        .map(|_| match next_random(&mut state) % 10 {
+            0 => Move::By {
+                dx: coord(&mut state),
+                dy: coord(&mut state),
+            },
+            1 => Move::To(Loc {
+                x: coord(&mut state).into(),
+                y: coord(&mut state).into(),
+            }),
+            _ => Move::Step {
+                dir: dirs[(next_random(&mut state) % dirs.len() as u64) as usize],
+                dist: 1 + (next_random(&mut state) % max_dist as u64) as u32,
+            },
+        })
+        .collect()
+}
+
+fn bench() {
+    for (count, max_dist) in [(1000, 1000), (100, 10000)] {
+        let moves = generate_moves(2022, count, max_dist);
+        for num_knots in [2, 10, 50] {
+            // counting is part of the time, the fast one leaves most of that until the end
+            let slow_time = Instant::now();
+            let mut slow = Rope::new(num_knots);
+            slow.run(&moves);
+            let slow_count = slow.tail_visited_count();
+            let slow_time = slow_time.elapsed();
+            let fast_time = Instant::now();
+            let mut fast = Rope::new(num_knots);
+            fast.run_fast(&moves);
+            let fast_count = fast.tail_visited_count();
+            let fast_time = fast_time.elapsed();
+            assert_eq!(slow_count, fast_count);
+            println!(
+                "{} moves up to {} long, {} knots: step by step {:?}, fast {:?} (tail visited {})",
+                count, max_dist, num_knots, slow_time, fast_time, fast_count
+            );
         }
-        //println!("results: {:?}, {:?}", h, knots);
-        //println!("tail locations: {:?}", tail_locations);
     }
-    tail_locations.len()
 }
 
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("bench") {
+        bench();
+        return;
+    }
+
+    // `cargo run -- draw <knots>` animates the rope in the terminal, one frame per step.
+    // `--moves` only draws after each move, `--delay <ms>` sets the speed, `--frames <dir>` writes
+    // the frames to files instead, and `--sample` uses the small example
+    if args.first().map(String::as_str) == Some("draw") {
+        let input = if args.iter().any(|arg| arg == "--sample") {
+            include_str!("input-sample.txt")
+        } else {
+            include_str!("input.txt")
+        };
+        let moves = parse(input);
+        let option = |name: &str| {
+            args.iter().position(|arg| arg == name).map(|i| {
+                args.get(i + 1)
+                    .map(String::as_str)
+                    .unwrap_or_else(|| panic!("{} needs a value", name))
+            })
+        };
+        let num_knots = args.get(1).and_then(|arg| arg.parse().ok()).unwrap_or(10);
+        let every = if args.iter().any(|arg| arg == "--moves") {
+            Every::Move
+        } else {
+            Every::Step
+        };
+        let delay = Duration::from_millis(
+            option("--delay").map_or(100, |ms| ms.parse().expect("delay must be a number")),
+        );
+        let mut rope = Rope::new(num_knots);
+        match option("--frames") {
+            Some(dir) => {
+                let mut index = 0;
+                render::animate(&mut rope, &moves, every, None, |frame| {
+                    render::save(Path::new(dir), index, frame).expect("failed to write frame");
+                    index += 1;
+                });
+                println!("wrote {} frames to {}", index, dir);
+            }
+            None => render::animate(&mut rope, &moves, every, None, |frame| {
+                render::play(frame, delay)
+            }),
+        }
+        return;
+    }
+
     let input = include_str!("input.txt");
     let moves = parse(input);
-    let num_t_loc = follow(moves, 9);
-    println!("num tail locations: {}", num_t_loc);
+
+    // `cargo run -- <knots>` simulates a rope of that many knots (head included) and lists every knot
+    if let Some(num_knots) = args.first() {
+        let num_knots = num_knots.parse().expect("number of knots must be a number");
+        let mut rope = Rope::new(num_knots);
+        rope.run_fast(&moves);
+        for knot in 0..num_knots {
+            let Loc { x, y } = rope.knots()[knot];
+            println!(
+                "knot {} ended at ({}, {}) and visited {}",
+                knot,
+                x,
+                y,
+                rope.visited_count(knot)
+            );
+        }
+        return;
+    }
+
+    // both parts come out of the same 10 knot rope: knot 1 is the 2 knot tail from part 1
+    let mut rope = Rope::new(10);
+    rope.run_fast(&moves);
+    println!("num tail locations (2 knots): {}", rope.visited_count(1));
+    println!("num tail locations: {}", rope.tail_visited_count());
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    // how many places the last of [num_knots] followers visits
+    fn follow(moves: Vec<Move>, num_knots: usize) -> usize {
+        let mut rope = Rope::new(num_knots + 1);
+        rope.run(&moves);
+        rope.tail_visited().len()
+    }
+
     #[test]
     fn test_step_t() {
         let h = Loc { x: 4, y: 2 };
@@ -114,6 +338,113 @@ mod tests {
         assert_eq!(step_t(t, h), Loc { x: 4, y: 1 });
     }
 
+    #[test]
+    fn test_step_t_diagonal_head() {
+        let t = Loc { x: 0, y: 0 };
+        assert_eq!(step_t(t, Loc { x: 2, y: 2 }), Loc { x: 1, y: 1 });
+        assert_eq!(step_t(t, Loc { x: -2, y: 1 }), Loc { x: -1, y: 1 });
+        assert_eq!(step_t(t, Loc { x: 1, y: -1 }), t);
+    }
+
+    #[test]
+    fn test_parse_commands() {
+        let moves = parse("R 4\nUL 300\n\nby 3 -2\nto -1 5");
+        assert_eq!(
+            moves,
+            vec![
+                Move::Step {
+                    dir: Right,
+                    dist: 4
+                },
+                Move::Step {
+                    dir: UpLeft,
+                    dist: 300
+                },
+                Move::By { dx: 3, dy: -2 },
+                Move::To(Loc { x: -1, y: 5 }),
+            ]
+        );
+        let shown: Vec<String> = moves.iter().map(|m| m.to_string()).collect();
+        assert_eq!(shown, vec!["R 4", "UL 300", "by 3 -2", "to -1 5"]);
+        assert_eq!(
+            parse("D 100000"),
+            vec![Move::Step {
+                dir: Down,
+                dist: 100000
+            }]
+        );
+    }
+
+    #[test]
+    fn test_legs() {
+        let head = Loc { x: 1, y: 1 };
+        assert_eq!(
+            Move::By { dx: 3, dy: -2 }.legs(head),
+            vec![(DownRight, 2), (Right, 1)]
+        );
+        assert_eq!(Move::To(Loc { x: 1, y: -3 }).legs(head), vec![(Down, 4)]);
+        assert_eq!(Move::To(head).legs(head), vec![]);
+    }
+
+    #[test]
+    fn test_past_i32() {
+        // the input is i32, but getting from one end to the other or going one further isn't
+        let far = Loc {
+            x: 2000000000,
+            y: 0,
+        };
+        assert_eq!(
+            Move::To(Loc {
+                x: -2000000000,
+                y: 0
+            })
+            .legs(far),
+            vec![(Left, 4000000000)]
+        );
+        let edge = Loc {
+            x: i32::MAX.into(),
+            y: i32::MIN.into(),
+        };
+        assert_eq!(
+            step_h(edge, DownRight),
+            Loc {
+                x: 2147483648,
+                y: -2147483649
+            }
+        );
+        let mut rope = Rope::new(2);
+        rope.run_fast(&parse("to 2000000000 0\nto -2000000000 0"));
+        assert_eq!(
+            rope.knots(),
+            &[
+                Loc {
+                    x: -2000000000,
+                    y: 0
+                },
+                Loc {
+                    x: -1999999999,
+                    y: 0
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_commands_match_plain_moves() {
+        // the same path written three ways leaves the same trail behind it
+        let plain = parse("UR 3\nR 2\nD 7\nL 12");
+        let relative = parse("by 5 3\nby 0 -7\nby -12 0");
+        let absolute = parse("to 5 3\nto 5 -4\nto -7 -4");
+        let mut expected = Rope::new(10);
+        expected.run(&plain);
+        for moves in [relative, absolute] {
+            let mut rope = Rope::new(10);
+            rope.run(&moves);
+            assert_eq!(rope.knots(), expected.knots());
+            assert_eq!(rope.tail_visited(), expected.tail_visited());
+        }
+    }
+
     #[test]
     fn test_step_follow() {
         let input = include_str!("input-sample.txt");
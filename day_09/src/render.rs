@@ -0,0 +1,220 @@
+use std::{collections::HashSet, fs, io, path::Path, thread, time::Duration};
+
+use crate::rope::Rope;
+use crate::{Loc, Move};
+
+// the part of the grid to draw, inclusive on every side
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bounds {
+    pub min_x: i64,
+    pub max_x: i64,
+    pub min_y: i64,
+    pub max_y: i64,
+}
+
+impl Bounds {
+    fn around(loc: Loc) -> Self {
+        Bounds {
+            min_x: loc.x,
+            max_x: loc.x,
+            min_y: loc.y,
+            max_y: loc.y,
+        }
+    }
+
+    fn include(&mut self, loc: Loc) {
+        self.min_x = self.min_x.min(loc.x);
+        self.max_x = self.max_x.max(loc.x);
+        self.min_y = self.min_y.min(loc.y);
+        self.max_y = self.max_y.max(loc.y);
+    }
+
+    // just big enough for every knot, the start and everywhere the tail has been
+    pub fn fit(rope: &Rope) -> Self {
+        let mut bounds = Bounds::around(Loc { x: 0, y: 0 });
+        for &loc in rope.knots().iter().chain(rope.tail_visited().iter()) {
+            bounds.include(loc);
+        }
+        bounds
+    }
+}
+
+// what goes in a knot's cell: H for the head, T for the tail of a two knot rope like in part 1,
+// otherwise the knot's number. after 9 they carry on through the alphabet
+fn knot_char(knot: usize, num_knots: usize) -> char {
+    match knot {
+        0 => 'H',
+        1 if num_knots == 2 => 'T',
+        1..=9 => (b'0' + knot as u8) as char,
+        10..=35 => (b'a' + (knot - 10) as u8) as char,
+        _ => '*',
+    }
+}
+
+// draws the rope the same way the puzzle does, with y going up the screen.
+// knots that share a cell show the one nearest the head, and they cover up the start (s)
+// which covers up everywhere the tail has been (#)
+pub fn draw(rope: &Rope, bounds: &Bounds, knots: bool, visited: bool) -> String {
+    let trail = if visited {
+        rope.tail_visited()
+    } else {
+        HashSet::new()
+    };
+    let mut lines = Vec::new();
+    for y in (bounds.min_y..=bounds.max_y).rev() {
+        let line: String = (bounds.min_x..=bounds.max_x)
+            .map(|x| {
+                let loc = Loc { x, y };
+                let knot = rope.knots().iter().position(|&knot| knot == loc);
+                match knot {
+                    Some(knot) if knots => knot_char(knot, rope.knots().len()),
+                    _ if loc == (Loc { x: 0, y: 0 }) => 's',
+                    _ if trail.contains(&loc) => '#',
+                    _ => '.',
+                }
+            })
+            .collect();
+        lines.push(line);
+    }
+    lines.join("\n")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Every {
+    Step,
+    Move,
+}
+
+// runs [moves] on [rope] and calls [on_frame] with a drawing after every step or every move.
+// without fixed [bounds] the view grows to fit everything seen so far, so it never shrinks while animating
+pub fn animate(
+    rope: &mut Rope,
+    moves: &[Move],
+    every: Every,
+    bounds: Option<Bounds>,
+    mut on_frame: impl FnMut(&str),
+) {
+    let mut seen = bounds.unwrap_or_else(|| Bounds::fit(rope));
+    let mut frame = |rope: &Rope, m: &Move| {
+        if bounds.is_none() {
+            for &loc in rope.knots() {
+                seen.include(loc);
+            }
+        }
+        on_frame(&format!(
+            "== {} ==\n\n{}\n",
+            m,
+            draw(rope, &seen, true, true)
+        ));
+    };
+    for m in moves {
+        for (dir, dist) in m.legs(rope.knots()[0]) {
+            for _ in 0..dist {
+                rope.step(dir);
+                if every == Every::Step {
+                    frame(rope, m);
+                }
+            }
+        }
+        if every == Every::Move {
+            frame(rope, m);
+        }
+    }
+}
+
+// redraws over the top of the last frame, waiting [delay] between them
+pub fn play(frame: &str, delay: Duration) {
+    print!("\x1b[2J\x1b[H{}", frame);
+    thread::sleep(delay);
+}
+
+// writes a frame to [dir] as frame_00000.txt, frame_00001.txt, ...
+pub fn save(dir: &Path, index: usize, frame: &str) -> io::Result<()> {
+    fs::write(dir.join(format!("frame_{:05}.txt", index)), frame)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    const SAMPLE_BOUNDS: Bounds = Bounds {
+        min_x: 0,
+        max_x: 5,
+        min_y: 0,
+        max_y: 4,
+    };
+
+    #[test]
+    fn test_first_move() {
+        let moves = parse(include_str!("input-sample.txt"));
+        let mut rope = Rope::new(2);
+        rope.apply(&moves[0]);
+        assert_eq!(
+            draw(&rope, &SAMPLE_BOUNDS, true, false),
+            "......
+......
+......
+......
+s..TH."
+        );
+        let mut rope = Rope::new(10);
+        rope.apply(&moves[0]);
+        assert_eq!(
+            draw(&rope, &SAMPLE_BOUNDS, true, false).lines().last(),
+            Some("4321H.") // 4 covers 5, 6, 7, 8, 9 and s
+        );
+    }
+
+    #[test]
+    fn test_visited() {
+        let moves = parse(include_str!("input-sample.txt"));
+        let mut rope = Rope::new(2);
+        rope.run(&moves);
+        // straight from the puzzle description
+        assert_eq!(
+            draw(&rope, &SAMPLE_BOUNDS, false, true),
+            "..##..
+...##.
+.####.
+....#.
+s###.."
+        );
+        // the puzzle leaves an empty column on the right, fitting it doesn't
+        assert_eq!(
+            Bounds::fit(&rope),
+            Bounds {
+                max_x: 4,
+                ..SAMPLE_BOUNDS
+            }
+        );
+    }
+
+    #[test]
+    fn test_animate() {
+        let moves = parse(include_str!("input-sample.txt"));
+        let mut frames = Vec::new();
+        animate(&mut Rope::new(2), &moves, Every::Step, None, |frame| {
+            frames.push(frame.to_string())
+        });
+        assert_eq!(frames.len(), 24);
+        assert_eq!(frames[0], "== R 4 ==\n\nTH\n");
+        let mut frames = Vec::new();
+        animate(
+            &mut Rope::new(2),
+            &moves,
+            Every::Move,
+            Some(SAMPLE_BOUNDS),
+            |frame| frames.push(frame.to_string()),
+        );
+        assert_eq!(frames.len(), moves.len());
+        assert!(frames[0].ends_with("\ns##TH.\n"));
+    }
+
+    #[test]
+    fn test_knot_chars() {
+        assert_eq!(knot_char(1, 10), '1');
+        assert_eq!(knot_char(10, 40), 'a');
+        assert_eq!(knot_char(36, 40), '*');
+    }
+}
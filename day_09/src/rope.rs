@@ -0,0 +1,395 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{step_h, step_t, Dir, Loc, Move};
+
+// a rope with any number of knots, knot 0 is the head and the last one is the tail.
+// every knot keeps track of everywhere it's been, so one run answers the question for all of them.
+// cells a knot passes one step at a time go in [visited], bulk slides are kept as straight runs in [slides]
+pub struct Rope {
+    knots: Vec<Loc>,
+    visited: Vec<HashSet<Loc>>,
+    slides: Vec<Vec<Slide>>,
+}
+
+// a straight run of [len] cells, starting from the one after [start]
+#[derive(Debug, Clone, Copy)]
+struct Slide {
+    start: Loc,
+    dir: Dir,
+    len: i64,
+}
+
+impl Rope {
+    pub fn new(num_knots: usize) -> Self {
+        assert!(num_knots > 0, "a rope needs at least one knot");
+        let start = Loc { x: 0, y: 0 };
+        Rope {
+            knots: vec![start; num_knots],
+            visited: vec![HashSet::from([start]); num_knots],
+            slides: vec![Vec::new(); num_knots],
+        }
+    }
+
+    pub fn knots(&self) -> &[Loc] {
+        &self.knots
+    }
+
+    // moves the head one step in [dir] and drags the rest of the rope along behind it
+    pub fn step(&mut self, dir: Dir) {
+        self.advance(dir);
+    }
+
+    // does a [step], and says whether every knot moved exactly the same way as the head
+    fn advance(&mut self, dir: Dir) -> bool {
+        let (dx, dy) = dir.offset();
+        self.knots[0] = step_h(self.knots[0], dir);
+        self.visited[0].insert(self.knots[0]);
+        let mut rigid = true;
+        for i in 1..self.knots.len() {
+            let knot = step_t(self.knots[i], self.knots[i - 1]);
+            if knot == self.knots[i] {
+                return false; // this one didn't move, so none of the ones behind it will either
+            }
+            rigid &= knot.x - self.knots[i].x == dx && knot.y - self.knots[i].y == dy;
+            self.knots[i] = knot;
+            self.visited[i].insert(knot);
+        }
+        rigid
+    }
+
+    // moves the whole rope [dist] steps in [dir] without touching the spacing between knots.
+    // the cells are only counted up at the end, so this doesn't depend on how far it goes
+    fn slide(&mut self, dir: Dir, dist: u64) {
+        if dist == 0 {
+            return;
+        }
+        let len = i64::try_from(dist).expect("moves can't be longer than i64::MAX");
+        let (dx, dy) = dir.offset();
+        for (knot, slides) in self.knots.iter_mut().zip(self.slides.iter_mut()) {
+            slides.push(Slide {
+                start: *knot,
+                dir,
+                len,
+            });
+            *knot = Loc {
+                x: knot.x + dx * len,
+                y: knot.y + dy * len,
+            };
+        }
+    }
+
+    pub fn apply(&mut self, m: &Move) {
+        for (dir, dist) in m.legs(self.knots[0]) {
+            for _ in 0..dist {
+                self.step(dir);
+            }
+        }
+    }
+
+    pub fn run<'a>(&mut self, moves: impl IntoIterator<Item = &'a Move>) {
+        for m in moves {
+            self.apply(m);
+        }
+    }
+
+    // gives exactly the same result as [run] but skips most of the work on long moves.
+    // once a step moves every knot the same way as the head the rope is stretched out behind it,
+    // and nothing about following depends on where the rope is, only on the gaps between knots.
+    // so every step after that in the same direction does the same thing, and the rest of the move
+    // can slide the whole rope along at once instead of working out each knot every step.
+    // the slides are only kept as straight runs, so use [visited_count] rather than [visited] to count them
+    pub fn run_fast<'a>(&mut self, moves: impl IntoIterator<Item = &'a Move>) {
+        for m in moves {
+            for (dir, dist) in m.legs(self.knots[0]) {
+                let mut remaining = dist;
+                while remaining > 0 {
+                    remaining -= 1;
+                    if self.advance(dir) {
+                        self.slide(dir, remaining);
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    fn check_knot(&self, knot: usize) {
+        assert!(
+            knot < self.knots.len(),
+            "knot {} isn't in a rope of {}",
+            knot,
+            self.knots.len()
+        );
+    }
+
+    // everywhere knot [knot] has been, including where it started.
+    // after [run_fast] this has to fill in every cell of every slide, use [visited_count] for just the number
+    pub fn visited(&self, knot: usize) -> HashSet<Loc> {
+        self.check_knot(knot);
+        let mut visited = self.visited[knot].clone();
+        for slide in self.slides[knot].iter() {
+            let (dx, dy) = slide.dir.offset();
+            visited.extend((1..=slide.len).map(|i| Loc {
+                x: slide.start.x + dx * i,
+                y: slide.start.y + dy * i,
+            }));
+        }
+        visited
+    }
+
+    pub fn tail_visited(&self) -> HashSet<Loc> {
+        self.visited(self.knots.len() - 1)
+    }
+
+    // how many cells knot [knot] has been in, without listing them
+    pub fn visited_count(&self, knot: usize) -> usize {
+        self.check_knot(knot);
+        if self.slides[knot].is_empty() {
+            return self.visited[knot].len();
+        }
+        Lines::new(&self.slides[knot]).count_with(&self.visited[knot])
+    }
+
+    pub fn tail_visited_count(&self) -> usize {
+        self.visited_count(self.knots.len() - 1)
+    }
+}
+
+// every cell is on one line of each of these, a slide runs along exactly one of them
+const ORIENTATIONS: [(i64, i64); 4] = [(1, 0), (0, 1), (1, 1), (1, -1)];
+
+// which line of orientation [o] a cell is on, and how far along it
+fn line_key(o: usize, loc: Loc) -> i64 {
+    match o {
+        0 => loc.y,
+        1 => loc.x,
+        2 => loc.y - loc.x,
+        _ => loc.y + loc.x,
+    }
+}
+
+fn along(o: usize, loc: Loc) -> i64 {
+    match o {
+        1 => loc.y,
+        _ => loc.x,
+    }
+}
+
+fn cell(o: usize, key: i64, along: i64) -> Loc {
+    match o {
+        0 => Loc { x: along, y: key },
+        1 => Loc { x: key, y: along },
+        2 => Loc {
+            x: along,
+            y: key + along,
+        },
+        _ => Loc {
+            x: along,
+            y: key - along,
+        },
+    }
+}
+
+// slides merged into non-overlapping runs of cells, [lines][orientation][key] is sorted (from, to) inclusive
+struct Lines {
+    lines: Vec<HashMap<i64, Vec<(i64, i64)>>>,
+}
+
+impl Lines {
+    fn new(slides: &[Slide]) -> Self {
+        let mut lines = vec![HashMap::<i64, Vec<(i64, i64)>>::new(); ORIENTATIONS.len()];
+        for slide in slides {
+            let (dx, dy) = slide.dir.offset();
+            let o = ORIENTATIONS
+                .iter()
+                .position(|&(ox, oy)| (ox, oy) == (dx, dy) || (ox, oy) == (-dx, -dy))
+                .expect("every direction is on one of the orientations");
+            let end = Loc {
+                x: slide.start.x + dx * slide.len,
+                y: slide.start.y + dy * slide.len,
+            };
+            let (a, b) = (along(o, slide.start), along(o, end));
+            // the start cell isn't part of the slide
+            let run = if a < b { (a + 1, b) } else { (b, a - 1) };
+            lines[o].entry(line_key(o, end)).or_default().push(run);
+        }
+        for runs in lines.iter_mut().flat_map(|by_key| by_key.values_mut()) {
+            runs.sort_unstable();
+            let mut merged: Vec<(i64, i64)> = Vec::with_capacity(runs.len());
+            for &(from, to) in runs.iter() {
+                match merged.last_mut() {
+                    Some(last) if from <= last.1 + 1 => last.1 = last.1.max(to),
+                    _ => merged.push((from, to)),
+                }
+            }
+            *runs = merged;
+        }
+        Lines { lines }
+    }
+
+    fn covers(&self, o: usize, loc: Loc) -> bool {
+        let Some(runs) = self.lines[o].get(&line_key(o, loc)) else {
+            return false;
+        };
+        let at = along(o, loc);
+        let i = runs.partition_point(|&(_, to)| to < at);
+        runs.get(i).is_some_and(|&(from, _)| from <= at)
+    }
+
+    // the size of the union of every run and [cells]
+    fn count_with(&self, cells: &HashSet<Loc>) -> usize {
+        let mut count: usize = self
+            .lines
+            .iter()
+            .flat_map(|by_key| by_key.values().flatten())
+            .map(|&(from, to)| (to - from + 1) as usize)
+            .sum();
+        // runs of different orientations can cross, and those cells have been counted once for each.
+        // two lines that aren't parallel cross at one point at most, which might fall between cells
+        let mut crossings: HashMap<Loc, u8> = HashMap::new();
+        for a in 0..ORIENTATIONS.len() {
+            for b in a + 1..ORIENTATIONS.len() {
+                for &key_a in self.lines[a].keys() {
+                    let base = line_key(b, cell(a, key_a, 0));
+                    let slope = line_key(b, cell(a, key_a, 1)) - base;
+                    for &key_b in self.lines[b].keys() {
+                        if (key_b - base) % slope != 0 {
+                            continue;
+                        }
+                        let at = (key_b - base) / slope;
+                        let loc = cell(a, key_a, at);
+                        if self.covers(a, loc) && self.covers(b, loc) {
+                            *crossings.entry(loc).or_default() |= (1 << a) | (1 << b);
+                        }
+                    }
+                }
+            }
+        }
+        count -= crossings
+            .values()
+            .map(|mask| mask.count_ones() as usize - 1)
+            .sum::<usize>();
+        count
+            + cells
+                .iter()
+                .filter(|&&loc| (0..ORIENTATIONS.len()).all(|o| !self.covers(o, loc)))
+                .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn test_every_knot_from_one_run() {
+        let moves = parse(include_str!("input-sample.txt"));
+        let mut rope = Rope::new(10);
+        rope.run(&moves);
+        // knot 1 follows the head the same way no matter how long the rope is, so it's part 1
+        assert_eq!(rope.visited(1).len(), 13);
+        assert_eq!(rope.tail_visited().len(), 1);
+        assert!(rope.visited(0).contains(&Loc { x: 4, y: 4 }));
+    }
+
+    #[test]
+    fn test_lengths_agree() {
+        let moves = parse(include_str!("input-sample.txt"));
+        let mut long = Rope::new(10);
+        long.run(&moves);
+        for num_knots in 1..=10 {
+            let mut short = Rope::new(num_knots);
+            short.run(&moves);
+            assert_eq!(short.tail_visited(), long.visited(num_knots - 1));
+            assert_eq!(short.knots(), &long.knots()[..num_knots]);
+        }
+    }
+
+    fn assert_same_visits(fast: &Rope, slow: &Rope) {
+        for knot in 0..slow.knots().len() {
+            let visited = slow.visited(knot);
+            assert_eq!(fast.visited(knot), visited, "knot {}", knot);
+            assert_eq!(fast.visited_count(knot), visited.len(), "knot {}", knot);
+        }
+    }
+
+    #[test]
+    fn test_run_fast() {
+        let moves = parse("R 5\nU 8\nL 8\nD 3\nR 17\nD 10\nL 25\nU 20");
+        let mut slow = Rope::new(10);
+        slow.run(&moves);
+        let mut fast = Rope::new(10);
+        fast.run_fast(&moves);
+        assert_eq!(fast.tail_visited().len(), 36);
+        assert_eq!(fast.knots(), slow.knots());
+        assert_same_visits(&fast, &slow);
+    }
+
+    #[test]
+    fn test_run_fast_against_steps() {
+        for seed in 1..20 {
+            let moves = crate::generate_moves(seed, 60, 30);
+            for num_knots in [1, 2, 3, 10, 25] {
+                let mut slow = Rope::new(num_knots);
+                slow.run(&moves);
+                let mut fast = Rope::new(num_knots);
+                fast.run_fast(&moves);
+                assert_eq!(fast.knots(), slow.knots(), "seed {}", seed);
+                assert_same_visits(&fast, &slow);
+            }
+        }
+    }
+
+    #[test]
+    fn test_crossing_slides() {
+        // a star of long moves through the middle, every orientation crosses every other one there
+        let moves =
+            parse("R 50\nL 100\nR 50\nU 50\nD 100\nU 50\nUR 50\nDL 100\nUR 50\nUL 50\nDR 100");
+        let mut slow = Rope::new(3);
+        slow.run(&moves);
+        let mut fast = Rope::new(3);
+        fast.run_fast(&moves);
+        assert_same_visits(&fast, &slow);
+    }
+
+    #[test]
+    fn test_slides_past_i32() {
+        // the same shape small enough to step through, to check the sums below
+        let small = parse("R 7\nR 7\nR 10\nUR 7\nUR 5");
+        let mut slow = Rope::new(3);
+        slow.run(&small);
+        assert_eq!(slow.tail_visited().len(), 2 * 7 + 9 + 12);
+        // each move fits in i32 but together they go well past it
+        let moves = parse("R 2147483647\nR 2147483647\nR 10\nUR 2147483647\nUR 5");
+        let mut rope = Rope::new(3);
+        rope.run_fast(&moves);
+        let head = Loc {
+            x: 3 * 2147483647 + 15,
+            y: 2147483652,
+        };
+        assert_eq!(rope.knots()[0], head);
+        // a rope lying flat stays flat when the head goes diagonally
+        assert_eq!(
+            rope.knots()[2],
+            Loc {
+                x: head.x - 2,
+                y: head.y
+            }
+        );
+        // the tail goes along the bottom until the head turns, then diagonally up
+        assert_eq!(
+            rope.tail_visited_count() as i64,
+            2 * 2147483647 + 9 + 2147483652
+        );
+    }
+
+    #[test]
+    fn test_single_knot() {
+        let mut rope = Rope::new(1);
+        rope.step(Dir::Right);
+        rope.step(Dir::Up);
+        assert_eq!(rope.knots(), &[Loc { x: 1, y: 1 }]);
+        assert_eq!(rope.tail_visited().len(), 3);
+    }
+}
@@ -0,0 +1,561 @@
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    fmt,
+};
+
+use crate::cpu::{find_spec, parse_instruction, Arg, Instruction};
+
+// on top of plain instructions, programs can have
+//   comments          `# like this` or `; like this`
+//   labels            `loop:` on its own line or in front of an instruction, and jumps can use them
+//   repeats           `repeat 3` ... `end`
+//   macros            `macro draw n` ... `end`, then `draw 5`. `$n` in the body is replaced by the argument
+// macros have to be defined at the top level, but they can be used anywhere after that, even in other macros.
+// labels in a macro or a repeat only belong to that copy of it, so each copy can loop back to its own start
+
+#[derive(Debug, PartialEq)]
+pub struct AsmError {
+    pub line: usize, // counting from 1
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+fn error<T>(line: usize, message: impl Into<String>) -> Result<T, AsmError> {
+    Err(AsmError {
+        line,
+        message: message.into(),
+    })
+}
+
+type Line = (usize, String);
+
+struct Macro {
+    params: Vec<String>,
+    body: Vec<Line>,
+}
+
+// macros that call each other forever would never finish expanding
+const MAX_DEPTH: usize = 64;
+// and ones that call each other a few times over, or repeats inside repeats, can still get huge.
+// a program that long couldn't finish inside the default cycle limit anyway
+const MAX_LINES: usize = 1_000_000;
+const MAX_COPIES: usize = 1_000_000;
+
+fn first_word(text: &str) -> &str {
+    text.split_whitespace().next().unwrap_or("")
+}
+
+fn is_name(word: &str) -> bool {
+    let mut chars = word.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// the index of the `end` that closes the block starting at [start]
+fn block_end(lines: &[Line], start: usize) -> Result<usize, AsmError> {
+    let mut depth = 0;
+    for (i, (_, text)) in lines.iter().enumerate().skip(start) {
+        match first_word(text) {
+            "repeat" | "macro" => depth += 1,
+            "end" => {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    error(lines[start].0, "this block never ends")
+}
+
+// takes the macro definitions out of [lines], leaving everything else
+fn collect_macros(lines: Vec<Line>) -> Result<(HashMap<String, Macro>, Vec<Line>), AsmError> {
+    let mut macros = HashMap::new();
+    let mut rest = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let (number, text) = &lines[i];
+        match first_word(text) {
+            "macro" => {}
+            // left whole, so a macro inside one gets complained about when it's expanded
+            "repeat" => {
+                let end = block_end(&lines, i)?;
+                rest.extend_from_slice(&lines[i..=end]);
+                i = end + 1;
+                continue;
+            }
+            _ => {
+                rest.push(lines[i].clone());
+                i += 1;
+                continue;
+            }
+        }
+        let end = block_end(&lines, i)?;
+        let mut words = text.split_whitespace().skip(1);
+        let name = words
+            .next()
+            .ok_or(AsmError {
+                line: *number,
+                message: "the macro needs a name".to_string(),
+            })?
+            .to_string();
+        let params: Vec<String> = words.map(str::to_string).collect();
+        if !is_name(&name) || params.iter().any(|param| !is_name(param)) {
+            return error(*number, format!("`{}` isn't a good macro definition", text));
+        }
+        if find_spec(&name).is_some() || ["repeat", "macro", "end"].contains(&name.as_str()) {
+            return error(*number, format!("{} is already an instruction", name));
+        }
+        if macros.contains_key(&name) {
+            return error(*number, format!("macro {} is defined twice", name));
+        }
+        let body = lines[i + 1..end].to_vec();
+        macros.insert(name, Macro { params, body });
+        i = end + 1;
+    }
+    Ok((macros, rest))
+}
+
+// whether [words] is an instruction that ends in a jump offset, which can be a label
+fn is_jump(words: &[&str]) -> bool {
+    words
+        .first()
+        .and_then(|word| find_spec(word))
+        .is_some_and(|(spec, _)| spec.args.last() == Some(&Arg::Offset))
+}
+
+// gives the labels defined in [lines] (but not in blocks inside them) a name of their own for copy number [scope].
+// the @ can't be typed in a label, so it can't clash with anything
+fn scoped(lines: &[Line], scope: usize) -> Vec<Line> {
+    let mut local = HashSet::new();
+    let mut depth = 0;
+    for (_, text) in lines {
+        match first_word(text) {
+            "repeat" | "macro" => depth += 1,
+            "end" => depth -= 1,
+            _ if depth > 0 => {}
+            _ => local.extend(text.strip_suffix(':')),
+        }
+    }
+    let rename = |label: &str| format!("{}@{}", label, scope);
+    lines
+        .iter()
+        .map(|(number, text)| {
+            let mut words: Vec<&str> = text.split_whitespace().collect();
+            let text = match text.strip_suffix(':') {
+                Some(label) if local.contains(label) => format!("{}:", rename(label)),
+                _ if is_jump(&words) && words.last().is_some_and(|word| local.contains(word)) => {
+                    let label = rename(words.pop().unwrap());
+                    format!("{} {}", words.join(" "), label)
+                }
+                _ => text.clone(),
+            };
+            (*number, text)
+        })
+        .collect()
+}
+
+// counts one more copy of a repeat or macro body, and gives back its number
+fn next_copy(scopes: &mut usize, line: usize) -> Result<usize, AsmError> {
+    *scopes += 1;
+    if *scopes > MAX_COPIES {
+        return error(
+            line,
+            format!(
+                "repeats and macros are written out more than {} times",
+                MAX_COPIES
+            ),
+        );
+    }
+    Ok(*scopes)
+}
+
+// copies [lines] into [out] with every repeat and macro call written out in full.
+// [scopes] counts the copies so far
+fn expand(
+    lines: &[Line],
+    macros: &HashMap<String, Macro>,
+    depth: usize,
+    scopes: &mut usize,
+    out: &mut Vec<Line>,
+) -> Result<(), AsmError> {
+    let mut i = 0;
+    while i < lines.len() {
+        let (number, text) = &lines[i];
+        let mut words = text.split_whitespace();
+        let word = words.next().unwrap_or("");
+        if word == "repeat" {
+            let end = block_end(lines, i)?;
+            let count: usize = match (words.next().map(str::parse), words.next()) {
+                (Some(Ok(count)), None) => count,
+                _ => return error(*number, "repeat needs a count"),
+            };
+            for _ in 0..count {
+                let scope = next_copy(scopes, *number)?;
+                expand(
+                    &scoped(&lines[i + 1..end], scope),
+                    macros,
+                    depth,
+                    scopes,
+                    out,
+                )?;
+            }
+            i = end + 1;
+            continue;
+        }
+        match word {
+            "end" => return error(*number, "end without a repeat or macro"),
+            "macro" => return error(*number, "macros have to be defined at the top level"),
+            _ => {}
+        }
+        if let Some(m) = macros.get(word) {
+            if depth >= MAX_DEPTH {
+                return error(*number, format!("{} keeps calling itself", word));
+            }
+            let args: Vec<&str> = words.collect();
+            if args.len() != m.params.len() {
+                return error(
+                    *number,
+                    format!(
+                        "{} takes {} argument(s) but got {}",
+                        word,
+                        m.params.len(),
+                        args.len()
+                    ),
+                );
+            }
+            // longest names first so that $n doesn't eat the start of $name
+            let mut params: Vec<(&String, &str)> = m.params.iter().zip(args).collect();
+            params.sort_by_key(|(param, _)| std::cmp::Reverse(param.len()));
+            let body: Vec<Line> = m
+                .body
+                .iter()
+                .map(|(_, line)| {
+                    let text = params.iter().fold(line.clone(), |text, (param, arg)| {
+                        text.replace(&format!("${}", param), arg)
+                    });
+                    (*number, text) // errors point at the call
+                })
+                .collect();
+            let scope = next_copy(scopes, *number)?;
+            expand(&scoped(&body, scope), macros, depth + 1, scopes, out)?;
+        } else if out.len() == MAX_LINES {
+            return error(
+                *number,
+                format!(
+                    "the program is more than {} lines once it's written out",
+                    MAX_LINES
+                ),
+            );
+        } else {
+            out.push(lines[i].clone());
+        }
+        i += 1;
+    }
+    Ok(())
+}
+
+// the label at the start of [text] if there is one, and what comes after it
+fn split_label(text: &str) -> Option<(&str, &str)> {
+    let word = first_word(text);
+    let label = word.strip_suffix(':')?;
+    Some((label, text.trim_start()[word.len()..].trim()))
+}
+
+pub fn assemble(source: &str) -> Result<Vec<Instruction>, AsmError> {
+    // every label gets a line to itself, so `loop: repeat 2` is still seen as a repeat
+    let mut lines: Vec<Line> = Vec::new();
+    for (i, line) in source.lines().enumerate() {
+        let mut code = line.split(['#', ';']).next().unwrap_or("").trim();
+        while let Some((label, rest)) = split_label(code) {
+            if !is_name(label) {
+                return error(i + 1, format!("`{}` can't be a label", label));
+            }
+            lines.push((i + 1, format!("{}:", label)));
+            code = rest;
+        }
+        if !code.is_empty() {
+            lines.push((i + 1, code.to_string()));
+        }
+    }
+    let (macros, lines) = collect_macros(lines)?;
+    let mut expanded = Vec::new();
+    expand(&lines, &macros, 0, &mut 0, &mut expanded)?;
+
+    let mut labels: HashMap<&str, usize> = HashMap::new();
+    let mut code: Vec<(usize, &str)> = Vec::new();
+    for (number, text) in expanded.iter() {
+        match text.strip_suffix(':') {
+            Some(label) => {
+                if labels.insert(label, code.len()).is_some() {
+                    let name = label.split('@').next().unwrap();
+                    return error(*number, format!("label {} is defined twice", name));
+                }
+            }
+            None => code.push((*number, text)),
+        }
+    }
+
+    code.iter()
+        .enumerate()
+        .map(|(index, (number, text))| {
+            let mut words: Vec<String> = text.split_whitespace().map(str::to_string).collect();
+            // swap a label for how far away it is
+            let takes_offset = is_jump(&words.iter().map(String::as_str).collect::<Vec<_>>());
+            if takes_offset {
+                if let Some(&target) = words.last().and_then(|word| labels.get(word.as_str())) {
+                    let offset = target as isize - index as isize;
+                    *words.last_mut().unwrap() = offset.to_string();
+                }
+            }
+            parse_instruction(&words.join(" ")).or_else(|message| error(*number, message))
+        })
+        .collect()
+}
+
+// writes [instructions] back out as source, one per line. jumps that land inside the program
+// get a label, named after where they are, so it reads a bit better and assembles back to the same thing
+pub fn disassemble(instructions: &[Instruction]) -> String {
+    let targets: BTreeSet<usize> = instructions
+        .iter()
+        .enumerate()
+        .filter_map(|(index, instruction)| {
+            let target = index as isize + instruction.jump_offset()?;
+            (0..=instructions.len() as isize)
+                .contains(&target)
+                .then_some(target as usize)
+        })
+        .collect();
+    let label = |index: usize| format!("at_{}", index);
+    let mut lines = Vec::new();
+    for (index, instruction) in instructions.iter().enumerate() {
+        if targets.contains(&index) {
+            lines.push(format!("{}:", label(index)));
+        }
+        let text = instruction.to_string();
+        let target = instruction
+            .jump_offset()
+            .map(|offset| index as isize + offset)
+            .filter(|&target| target >= 0 && targets.contains(&(target as usize)));
+        match (target, text.rsplit_once(' ')) {
+            (Some(target), Some((start, _))) => {
+                lines.push(format!("{} {}", start, label(target as usize)))
+            }
+            _ => lines.push(text),
+        }
+    }
+    if targets.contains(&instructions.len()) {
+        lines.push(format!("{}:", label(instructions.len())));
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::{Reg::*, CPU};
+
+    fn run(program: &[Instruction]) -> CPU {
+        let mut cpu = CPU::load(program.to_vec());
+        while cpu.tick().is_some() {}
+        cpu
+    }
+
+    #[test]
+    fn test_plain_program() {
+        let input = include_str!("input-sample.txt");
+        let program = assemble(input).unwrap();
+        assert_eq!(program.len(), 146);
+        assert_eq!(program[0], Instruction::Add(X, 15));
+        // no jumps, so it comes back out exactly as it went in
+        let lines: Vec<&str> = input.lines().map(str::trim).collect();
+        assert_eq!(disassemble(&program), lines.join("\n"));
+    }
+
+    #[test]
+    fn test_comments_labels_and_blank_lines() {
+        let program = assemble(
+            "# count down from 3 in y
+            addy 3 ; start here
+
+            loop: addx 2
+                  suby 1
+                  jnz y loop
+            done:",
+        )
+        .unwrap();
+        assert_eq!(
+            program,
+            vec![
+                Instruction::Add(Y, 3),
+                Instruction::Add(X, 2),
+                Instruction::Sub(Y, 1),
+                Instruction::Jnz(Y, -2),
+            ]
+        );
+        assert_eq!(run(&program).reg_x(), 7);
+    }
+
+    #[test]
+    fn test_forward_jumps() {
+        let program =
+            assemble("jmp skip\naddx 100\nskip: addx 1\njz w end\naddx 100\nend:").unwrap();
+        assert_eq!(program[0], Instruction::Jmp(2));
+        assert_eq!(program[3], Instruction::Jz(W, 2));
+        assert_eq!(run(&program).reg_x(), 2);
+    }
+
+    #[test]
+    fn test_repeat_and_macros() {
+        let program = assemble(
+            "macro move to from
+                addx $to
+                addx $from
+            end
+            macro twice n
+                repeat 2
+                    addx $n
+                end
+            end
+            repeat 3
+                noop
+            end
+            twice 4
+            move 10 -10",
+        )
+        .unwrap();
+        let shown: Vec<String> = program.iter().map(|i| i.to_string()).collect();
+        assert_eq!(
+            shown,
+            vec!["noop", "noop", "noop", "addx 4", "addx 4", "addx 10", "addx -10"]
+        );
+    }
+
+    #[test]
+    fn test_labels_in_blocks() {
+        // each call gets its own loop, so the second one doesn't jump back into the first
+        let program = assemble(
+            "macro countdown n
+                addw $n
+            loop:
+                addx 1
+                subw 1
+                jnz w loop
+            end
+            countdown 3
+            countdown 2
+            repeat 2
+                addw 2
+                again: subw 1
+                jnz w again
+            end",
+        )
+        .unwrap();
+        assert_eq!(program[3], Instruction::Jnz(W, -2));
+        assert_eq!(program[7], Instruction::Jnz(W, -2));
+        assert_eq!(program[10], Instruction::Jnz(W, -1));
+        assert_eq!(program[13], Instruction::Jnz(W, -1));
+        assert_eq!(run(&program).reg_x(), 6);
+        let error = assemble("macro twice\na: noop\na: noop\nend\ntwice").unwrap_err();
+        assert_eq!(error.message, "label a is defined twice");
+    }
+
+    #[test]
+    fn test_errors() {
+        let check = |source: &str, line: usize, message: &str| {
+            let error = assemble(source).unwrap_err();
+            assert_eq!(error.line, line, "{}", error);
+            assert!(error.message.contains(message), "{}", error);
+        };
+        check("noop\nfrob 3", 2, "unknown instruction frob");
+        check("jmp nowhere", 1, "unparseable jmp value nowhere");
+        check("a:\na: noop", 2, "label a is defined twice");
+        check("repeat 2\nnoop", 1, "never ends");
+        check("noop\nend", 2, "end without");
+        check("repeat 2\nmacro m\nend\nend", 2, "top level");
+        check(
+            "macro m a\naddx $a\nend\n\nm",
+            5,
+            "takes 1 argument(s) but got 0",
+        );
+        check("macro m\nm\nend\nm", 4, "keeps calling itself");
+        check("macro addx\nend", 1, "already an instruction");
+        check("macro m $n\nend", 1, "isn't a good macro definition");
+        check("1x: noop", 1, "can't be a label");
+    }
+
+    #[test]
+    fn test_size_limits() {
+        let check = |source: &str, line: usize, message: &str| {
+            let error = assemble(source).unwrap_err();
+            assert_eq!(error.line, line, "{}", error);
+            assert!(error.message.contains(message), "{}", error);
+        };
+        check(
+            "repeat 500000\nnoop\nnoop\nnoop\nend",
+            3,
+            "more than 1000000 lines",
+        );
+        check(
+            "repeat 100000\nrepeat 100000\nnoop\nend\nend",
+            2,
+            "more than 1000000 times",
+        );
+        // each one calls the next twice, so there'd be 2^64 noops
+        let mut doubling = "macro m0\nnoop\nend\n".to_string();
+        for i in 1..64 {
+            doubling += &format!("macro m{}\nm{}\nm{}\nend\n", i, i - 1, i - 1);
+        }
+        check(&(doubling + "m63"), 3 + 63 * 4 + 1, "more than 1000000");
+    }
+
+    #[test]
+    fn test_disassemble_labels() {
+        let program = vec![
+            Instruction::Add(Y, 3),
+            Instruction::Sub(Y, 1),
+            Instruction::Jnz(Y, -1),
+            Instruction::Jz(Y, 2),
+            Instruction::Noop,
+            Instruction::Jmp(-10),
+        ];
+        assert_eq!(
+            disassemble(&program),
+            "addy 3
+at_1:
+suby 1
+jnz y at_1
+jz y at_5
+noop
+at_5:
+jmp -10"
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let source = "
+            macro pulse n
+                addx $n
+                addx -$n
+            end
+            start: repeat 2
+                pulse 3
+            end
+            mov z x
+            jz w start
+            jmp 9";
+        let program = assemble(source).unwrap();
+        assert_eq!(assemble(&disassemble(&program)), Ok(program.clone()));
+        let again = disassemble(&assemble(&disassemble(&program)).unwrap());
+        assert_eq!(again, disassemble(&program));
+    }
+}
@@ -1,81 +1,404 @@
-#[derive(Debug, Eq, PartialEq)]
-enum Instruction {
+use std::fmt;
+
+use crate::trace::{signal_strength_sum, Samples, Trace};
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Reg {
+    X,
+    Y,
+    Z,
+    W,
+}
+
+use Reg::*;
+
+pub const REGISTERS: [Reg; 4] = [X, Y, Z, W];
+
+impl Reg {
+    pub fn from_name(name: &str) -> Option<Reg> {
+        REGISTERS.into_iter().find(|reg| reg.name() == name)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            X => "x",
+            Y => "y",
+            Z => "z",
+            W => "w",
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+// jumps are relative to the jump itself, so `jmp 0` spins forever and `jmp 1` is the same as a noop
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Instruction {
     Noop,
-    Addx(isize),
+    Add(Reg, isize),
+    Sub(Reg, isize),
+    Mul(Reg, isize),
+    Mov(Reg, Reg), // copies the second register into the first
+    Jmp(isize),
+    Jz(Reg, isize),
+    Jnz(Reg, isize),
 }
 
 use Instruction::*;
 
-fn parse(input: &str) -> Vec<Instruction> {
-    input
-        .lines()
-        .map(|line| line.trim())
-        .map(|line| {
-            if line.eq("noop") {
-                Noop
-            } else if line.starts_with("addx ") {
-                let (_, num) = line.split_at(5);
-                Addx(num.parse().expect("unparseable addx value"))
-            } else {
-                panic!("unable to parse line {}", line)
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Arg {
+    Reg,
+    Num,
+    Offset, // how far to jump, the assembler lets you use a label instead
+}
+
+// an instruction's register and numbers in the order they're written, a register suffix comes first
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Operand {
+    Reg(Reg),
+    Num(isize),
+}
+
+impl Operand {
+    fn reg(self) -> Reg {
+        match self {
+            Operand::Reg(reg) => reg,
+            Operand::Num(_) => panic!("expected a register"),
+        }
+    }
+
+    fn num(self) -> isize {
+        match self {
+            Operand::Num(num) => num,
+            Operand::Reg(_) => panic!("expected a number"),
+        }
+    }
+}
+
+type Registers = [isize; REGISTERS.len()];
+
+// everything about an instruction lives in its spec, so a new one only needs a variant and a spec.
+// with [register_suffix] the register goes on the end of the name, like `addx` or `suby`.
+// [build] and [operands] turn the variant to and from its operands, and [execute] carries it out,
+// giving back where to jump to if it jumps
+pub struct Spec {
+    pub name: &'static str,
+    pub cycles: usize,
+    pub register_suffix: bool,
+    pub args: &'static [Arg],
+    build: fn(&[Operand]) -> Instruction,
+    operands: fn(Instruction) -> Option<Vec<Operand>>,
+    execute: fn(&mut Registers, &[Operand]) -> Option<isize>,
+}
+
+impl fmt::Debug for Spec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Spec({})", self.name)
+    }
+}
+
+// arithmetic wraps around rather than stopping the program
+fn update(registers: &mut Registers, reg: Reg, f: impl Fn(isize) -> isize) -> Option<isize> {
+    registers[reg.index()] = f(registers[reg.index()]);
+    None
+}
+
+pub const INSTRUCTION_SET: [Spec; 8] = [
+    Spec {
+        name: "noop",
+        cycles: 1,
+        register_suffix: false,
+        args: &[],
+        build: |_| Noop,
+        operands: |i| matches!(i, Noop).then(Vec::new),
+        execute: |_, _| None,
+    },
+    Spec {
+        name: "add",
+        cycles: 2,
+        register_suffix: true,
+        args: &[Arg::Num],
+        build: |ops| Add(ops[0].reg(), ops[1].num()),
+        operands: |i| match i {
+            Add(reg, value) => Some(vec![Operand::Reg(reg), Operand::Num(value)]),
+            _ => None,
+        },
+        execute: |regs, ops| update(regs, ops[0].reg(), |x| x.wrapping_add(ops[1].num())),
+    },
+    Spec {
+        name: "sub",
+        cycles: 2,
+        register_suffix: true,
+        args: &[Arg::Num],
+        build: |ops| Sub(ops[0].reg(), ops[1].num()),
+        operands: |i| match i {
+            Sub(reg, value) => Some(vec![Operand::Reg(reg), Operand::Num(value)]),
+            _ => None,
+        },
+        execute: |regs, ops| update(regs, ops[0].reg(), |x| x.wrapping_sub(ops[1].num())),
+    },
+    Spec {
+        name: "mul",
+        cycles: 3,
+        register_suffix: true,
+        args: &[Arg::Num],
+        build: |ops| Mul(ops[0].reg(), ops[1].num()),
+        operands: |i| match i {
+            Mul(reg, value) => Some(vec![Operand::Reg(reg), Operand::Num(value)]),
+            _ => None,
+        },
+        execute: |regs, ops| update(regs, ops[0].reg(), |x| x.wrapping_mul(ops[1].num())),
+    },
+    Spec {
+        name: "mov",
+        cycles: 1,
+        register_suffix: false,
+        args: &[Arg::Reg, Arg::Reg],
+        build: |ops| Mov(ops[0].reg(), ops[1].reg()),
+        operands: |i| match i {
+            Mov(dest, src) => Some(vec![Operand::Reg(dest), Operand::Reg(src)]),
+            _ => None,
+        },
+        execute: |regs, ops| {
+            let value = regs[ops[1].reg().index()];
+            update(regs, ops[0].reg(), |_| value)
+        },
+    },
+    Spec {
+        name: "jmp",
+        cycles: 2,
+        register_suffix: false,
+        args: &[Arg::Offset],
+        build: |ops| Jmp(ops[0].num()),
+        operands: |i| match i {
+            Jmp(offset) => Some(vec![Operand::Num(offset)]),
+            _ => None,
+        },
+        execute: |_, ops| Some(ops[0].num()),
+    },
+    Spec {
+        name: "jz",
+        cycles: 2,
+        register_suffix: false,
+        args: &[Arg::Reg, Arg::Offset],
+        build: |ops| Jz(ops[0].reg(), ops[1].num()),
+        operands: |i| match i {
+            Jz(reg, offset) => Some(vec![Operand::Reg(reg), Operand::Num(offset)]),
+            _ => None,
+        },
+        execute: |regs, ops| (regs[ops[0].reg().index()] == 0).then(|| ops[1].num()),
+    },
+    Spec {
+        name: "jnz",
+        cycles: 2,
+        register_suffix: false,
+        args: &[Arg::Reg, Arg::Offset],
+        build: |ops| Jnz(ops[0].reg(), ops[1].num()),
+        operands: |i| match i {
+            Jnz(reg, offset) => Some(vec![Operand::Reg(reg), Operand::Num(offset)]),
+            _ => None,
+        },
+        execute: |regs, ops| (regs[ops[0].reg().index()] != 0).then(|| ops[1].num()),
+    },
+];
+
+impl Instruction {
+    pub fn spec(&self) -> &'static Spec {
+        self.decode().0
+    }
+
+    // the spec this is an instruction of, along with its operands
+    fn decode(&self) -> (&'static Spec, Vec<Operand>) {
+        INSTRUCTION_SET
+            .iter()
+            .find_map(|spec| Some((spec, (spec.operands)(*self)?)))
+            .expect("every instruction has a spec")
+    }
+
+    pub fn cycles(&self) -> usize {
+        self.spec().cycles
+    }
+
+    // how far it jumps, if it's a jump
+    pub fn jump_offset(&self) -> Option<isize> {
+        let (spec, operands) = self.decode();
+        let at = spec.args.iter().position(|&arg| arg == Arg::Offset)?;
+        Some(operands[at + spec.register_suffix as usize].num())
+    }
+}
+
+// the way it would be written in a program, e.g. `addx -5` or `jnz y -2`
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (spec, operands) = self.decode();
+        let mut operands = operands.into_iter();
+        write!(f, "{}", spec.name)?;
+        if spec.register_suffix {
+            write!(f, "{}", operands.next().unwrap().reg().name())?;
+        }
+        for operand in operands {
+            match operand {
+                Operand::Reg(reg) => write!(f, " {}", reg.name())?,
+                Operand::Num(num) => write!(f, " {}", num)?,
             }
-        })
-        .collect()
+        }
+        Ok(())
+    }
+}
+
+// splits a name like `addx` into its spec and register
+pub fn find_spec(name: &str) -> Option<(&'static Spec, Option<Reg>)> {
+    INSTRUCTION_SET.iter().find_map(|spec| {
+        if spec.register_suffix {
+            let reg = Reg::from_name(name.strip_prefix(spec.name)?)?;
+            Some((spec, Some(reg)))
+        } else {
+            (spec.name == name).then_some((spec, None))
+        }
+    })
+}
+
+// one instruction, like `addx -5` or `jnz y -2`
+pub fn parse_instruction(line: &str) -> Result<Instruction, String> {
+    let mut parts = line.split_whitespace();
+    let name = parts.next().ok_or("there's no instruction")?;
+    let (spec, suffix) = find_spec(name).ok_or(format!("unknown instruction {}", name))?;
+    let args: Vec<&str> = parts.collect();
+    if args.len() != spec.args.len() {
+        return Err(format!(
+            "{} takes {} argument(s) but got {}",
+            spec.name,
+            spec.args.len(),
+            args.len()
+        ));
+    }
+    let mut operands: Vec<Operand> = suffix.map(Operand::Reg).into_iter().collect();
+    for (&arg, text) in spec.args.iter().zip(args) {
+        operands.push(match arg {
+            Arg::Reg => {
+                Operand::Reg(Reg::from_name(text).ok_or(format!("unknown register {}", text))?)
+            }
+            Arg::Num | Arg::Offset => Operand::Num(
+                text.parse()
+                    .map_err(|_| format!("unparseable {} value {}", name, text))?,
+            ),
+        });
+    }
+    Ok((spec.build)(&operands))
 }
 
+// anything the assembler understands, so comments, labels and macros too
+fn parse(input: &str) -> Vec<Instruction> {
+    crate::asm::assemble(input).unwrap_or_else(|error| panic!("{}", error))
+}
+
+// programs can loop forever now, so they're stopped after this many cycles unless told otherwise
+pub const CYCLE_LIMIT: usize = 1_000_000;
+
+#[allow(clippy::upper_case_acronyms)]
 pub struct CPU {
     instructions: Vec<Instruction>,
-    pub reg_x: isize,
+    registers: Registers,
     pub cycle: usize,
     instruction: usize,
-    processing: bool,
+    busy: usize, // how many cycles have been spent on the current instruction so far
+    limit: usize,
 }
 
 impl CPU {
     pub fn init(input: &str) -> Self {
+        CPU::load(parse(input))
+    }
+
+    pub fn load(instructions: Vec<Instruction>) -> Self {
+        let mut registers = [0; REGISTERS.len()];
+        registers[X.index()] = 1;
         CPU {
-            instructions: parse(input),
-            reg_x: 1,
+            instructions,
+            registers,
             cycle: 1,
             instruction: 0,
-            processing: false,
+            busy: 0,
+            limit: CYCLE_LIMIT,
         }
     }
 
-    pub fn tick(self: &mut Self) -> Option<()> {
-        let instruction = self.instructions.get(self.instruction)?;
-        match instruction {
-            Noop => {
-                self.instruction = self.instruction + 1;
-                self.processing = false;
-            }
-            Addx(value) => {
-                if self.processing {
-                    self.processing = false;
-                    self.reg_x = self.reg_x + value;
-                    self.instruction = self.instruction + 1;
-                } else {
-                    self.processing = true;
-                }
-            }
+    pub fn set_cycle_limit(&mut self, limit: usize) {
+        self.limit = limit;
+    }
+
+    // whether it was stopped by the cycle limit rather than getting to the end of the program
+    pub fn out_of_cycles(&self) -> bool {
+        self.cycle > self.limit && self.instructions.get(self.instruction).is_some()
+    }
+
+    // an error if the program didn't finish by itself
+    pub fn finished(&self) -> Result<(), String> {
+        if self.out_of_cycles() {
+            return Err(format!(
+                "still running after {} cycles, it's probably stuck in a loop",
+                self.limit
+            ));
         }
-        self.cycle = self.cycle + 1;
-        Some(())
+        Ok(())
     }
 
-    pub fn get_cumulative_signal_stregnths(self: &mut Self) -> isize {
-        let mut cumlutative_strength = 0;
-        while self.tick() == Some(()) {
-            if self.cycle >= 20 && (self.cycle - 20) % 40 == 0 {
-                let strength = self.reg_x * self.cycle as isize;
-                println!(
-                    "strength at cycle {} is {} (reg_x: {})",
-                    self.cycle, strength, self.reg_x
-                );
-                cumlutative_strength = cumlutative_strength + strength;
-            }
+    pub fn reg(&self, reg: Reg) -> isize {
+        self.registers[reg.index()]
+    }
+
+    pub fn reg_x(&self) -> isize {
+        self.reg(X)
+    }
+
+    // the index of the instruction being run, or that will run next
+    pub fn instruction_index(&self) -> usize {
+        self.instruction
+    }
+
+    // None once the program has ended or run out of cycles
+    pub fn current(&self) -> Option<Instruction> {
+        if self.cycle > self.limit {
+            return None;
         }
-        cumlutative_strength
+        self.instructions.get(self.instruction).copied()
+    }
+
+    // how many cycles of the current instruction are already done
+    pub fn cycles_done(&self) -> usize {
+        self.busy
+    }
+
+    // finishes the current cycle, and the current instruction too if this was its last cycle.
+    // None once it has run off the end of the program, or out of cycles
+    pub fn tick(&mut self) -> Option<()> {
+        let instruction = self.current()?;
+        self.busy += 1;
+        if self.busy == instruction.cycles() {
+            self.busy = 0;
+            self.execute(instruction);
+        }
+        self.cycle += 1;
+        Some(())
+    }
+
+    fn execute(&mut self, instruction: Instruction) {
+        let (spec, operands) = instruction.decode();
+        let jump = (spec.execute)(&mut self.registers, &operands).unwrap_or(1);
+        // jumping before the start ends the program the same way as running off the end
+        self.instruction = (self.instruction as isize)
+            .checked_add(jump)
+            .and_then(|target| target.try_into().ok())
+            .unwrap_or(usize::MAX);
+    }
+
+    // the sum of the signal strengths at the cycles the puzzle asks for, running the rest of the program
+    pub fn get_cumulative_signal_stregnths(&mut self) -> Result<isize, String> {
+        let sum = signal_strength_sum(Trace::new(self), &Samples::PUZZLE);
+        self.finished().map(|_| sum)
     }
 }
 
@@ -83,12 +406,44 @@ impl CPU {
 mod tests {
     use super::*;
 
+    fn run(cpu: &mut CPU) {
+        while cpu.tick() == Some(()) {}
+    }
+
     #[test]
     fn test_parse() {
         let input = "noop
         addx 3
         addx -5";
-        assert_eq!(parse(input), vec![Noop, Addx(3), Addx(-5)]);
+        assert_eq!(parse(input), vec![Noop, Add(X, 3), Add(X, -5)]);
+    }
+
+    #[test]
+    fn test_parse_new_instructions() {
+        let input = "suby 2
+        mulw -3
+        mov z x
+
+        jmp -1
+        jz y 4
+        jnz w -2";
+        assert_eq!(
+            parse(input),
+            vec![
+                Sub(Y, 2),
+                Mul(W, -3),
+                Mov(Z, X),
+                Jmp(-1),
+                Jz(Y, 4),
+                Jnz(W, -2)
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "takes 2 argument(s)")]
+    fn test_parse_wrong_args() {
+        parse("jz 4");
     }
 
     #[test]
@@ -98,23 +453,99 @@ mod tests {
         addx 3
         addx -5",
         );
-        assert_eq!(cpu.reg_x, 1); // initial state
+        assert_eq!(cpu.reg_x(), 1); // initial state
         cpu.tick(); // finish noop
-        assert_eq!(cpu.reg_x, 1);
+        assert_eq!(cpu.reg_x(), 1);
         cpu.tick(); // begin addx 3
-        assert_eq!(cpu.reg_x, 1);
+        assert_eq!(cpu.reg_x(), 1);
         cpu.tick(); // finish addx 3
-        assert_eq!(cpu.reg_x, 4);
+        assert_eq!(cpu.reg_x(), 4);
         cpu.tick(); // begin addx -5
-        assert_eq!(cpu.reg_x, 4);
+        assert_eq!(cpu.reg_x(), 4);
         cpu.tick(); // finish addx -5
-        assert_eq!(cpu.reg_x, -1);
+        assert_eq!(cpu.reg_x(), -1);
+    }
+
+    #[test]
+    fn test_display() {
+        let input = "noop\naddx -5\nsuby 2\nmulw 3\nmov z x\njmp -1\njz y 4\njnz w -2";
+        let shown: Vec<String> = parse(input).iter().map(|i| i.to_string()).collect();
+        assert_eq!(shown.join("\n"), input);
+    }
+
+    #[test]
+    fn test_cycle_counts() {
+        let mut cpu = CPU::init("mulx 5\nnoop");
+        cpu.tick();
+        cpu.tick();
+        assert_eq!(cpu.reg_x(), 1);
+        cpu.tick();
+        assert_eq!(cpu.reg_x(), 5);
+        run(&mut cpu);
+        assert_eq!(cpu.cycle, 5); // 3 for mulx and 1 for noop, starting at 1
+    }
+
+    #[test]
+    fn test_loop() {
+        // adds 3 to x four times, counting down in y
+        let mut cpu = CPU::init(
+            "addy 4
+            addx 3
+            suby 1
+            jnz y -2
+            mov z x",
+        );
+        run(&mut cpu);
+        assert_eq!(cpu.reg_x(), 13);
+        assert_eq!(cpu.reg(Y), 0);
+        assert_eq!(cpu.reg(Z), 13);
+        assert_eq!(cpu.cycle, 1 + 2 + 4 * (2 + 2 + 2) + 1);
+    }
+
+    #[test]
+    fn test_jump_out() {
+        let mut cpu = CPU::init("jz w -5\naddx 1");
+        run(&mut cpu);
+        assert_eq!(cpu.reg_x(), 1);
+        assert_eq!(cpu.cycle, 3);
     }
 
     #[test]
     fn test_cm_str() {
         let input = include_str!("input-sample.txt");
         let mut cpu = CPU::init(input);
-        assert_eq!(cpu.get_cumulative_signal_stregnths(), 13140);
+        assert_eq!(cpu.get_cumulative_signal_stregnths(), Ok(13140));
+    }
+
+    #[test]
+    fn test_overflow_wraps() {
+        let mut cpu = CPU::init("mulx 1000000000\nmulx 1000000000\nmulx 1000000000\naddx 1");
+        run(&mut cpu);
+        assert_eq!(
+            cpu.reg_x(),
+            1_000_000_000_isize.wrapping_pow(3).wrapping_add(1)
+        );
+        let mut cpu = CPU::init("subx 3\nsubx 9223372036854775807");
+        run(&mut cpu);
+        assert_eq!(cpu.reg_x(), isize::MAX);
+    }
+
+    #[test]
+    fn test_cycle_limit() {
+        let mut cpu = CPU::init("addx 1\nloop: jmp loop");
+        assert_eq!(
+            cpu.get_cumulative_signal_stregnths(),
+            Err("still running after 1000000 cycles, it's probably stuck in a loop".to_string())
+        );
+        assert_eq!(cpu.current(), None);
+        let mut cpu = CPU::init("addx 1\nnoop");
+        cpu.set_cycle_limit(3);
+        run(&mut cpu);
+        assert_eq!(cpu.finished(), Ok(()));
+        cpu = CPU::init("addx 1\nnoop");
+        cpu.set_cycle_limit(2);
+        run(&mut cpu);
+        assert!(cpu.out_of_cycles());
+        assert_eq!(cpu.reg_x(), 2);
     }
 }
@@ -0,0 +1,434 @@
+use std::{
+    fmt,
+    io::{self, BufRead, Write},
+};
+
+use crate::cpu::{Reg, CPU, REGISTERS};
+use crate::gpu::Screen;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Breakpoint {
+    Cycle(usize),       // stops at the start of this cycle
+    Instruction(usize), // stops when the instruction at this index is about to start
+    Value(Reg, isize),  // stops when the register changes to this value
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Breakpoint::Cycle(cycle) => write!(f, "cycle {}", cycle),
+            Breakpoint::Instruction(index) => write!(f, "ip {}", index),
+            Breakpoint::Value(reg, value) => write!(f, "{} {}", reg.name(), value),
+        }
+    }
+}
+
+// a sum of products of numbers, registers, `cycle`, `ip`, `bx` and `by` (where the beam is),
+// e.g. `x * cycle` is the signal strength. it wraps around like the cpu does
+fn eval(cpu: &CPU, screen: &Screen, expr: &str) -> Result<isize, String> {
+    let spaced = expr
+        .replace('+', " + ")
+        .replace('-', " - ")
+        .replace('*', " * ");
+    let mut total: isize = 0;
+    let mut sign: isize = 1;
+    let mut product: Option<isize> = None;
+    let mut expect_value = true;
+    for token in spaced.split_whitespace() {
+        match (token, expect_value) {
+            ("-", true) => sign = -sign,
+            ("+" | "-", false) => {
+                total = total.wrapping_add(sign.wrapping_mul(product.take().unwrap_or(0)));
+                sign = if token == "-" { -1 } else { 1 };
+                expect_value = true;
+            }
+            ("*", false) => expect_value = true,
+            (_, true) => {
+                let (bx, by) = screen.beam(cpu.cycle);
+                let value = match token {
+                    "cycle" => cpu.cycle as isize,
+                    "ip" => cpu.instruction_index() as isize,
+                    "bx" => bx as isize,
+                    "by" => by as isize,
+                    _ => match Reg::from_name(token) {
+                        Some(reg) => cpu.reg(reg),
+                        None => token
+                            .parse()
+                            .map_err(|_| format!("don't know what `{}` is", token))?,
+                    },
+                };
+                product = Some(product.map_or(value, |product| product.wrapping_mul(value)));
+                expect_value = false;
+            }
+            _ => return Err(format!("unexpected `{}` in `{}`", token, expr)),
+        }
+    }
+    if expect_value {
+        return Err(format!("`{}` isn't finished", expr));
+    }
+    Ok(total.wrapping_add(sign.wrapping_mul(product.unwrap_or(0))))
+}
+
+pub struct Debugger {
+    pub cpu: CPU,
+    screen: Screen, // for working out where the beam is
+    breakpoints: Vec<Breakpoint>,
+    watches: Vec<String>,
+}
+
+// why running stopped
+#[derive(Debug, PartialEq)]
+pub enum Stop {
+    Done,       // did as many cycles or instructions as asked
+    Hit(usize), // the breakpoint at this index
+    Halted,     // ran off the end of the program
+    Stuck,      // ran into the cycle limit
+}
+
+impl Debugger {
+    pub fn new(cpu: CPU, screen: Screen) -> Self {
+        Debugger {
+            cpu,
+            screen,
+            breakpoints: Vec::new(),
+            watches: Vec::new(),
+        }
+    }
+
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) -> usize {
+        self.breakpoints.push(breakpoint);
+        self.breakpoints.len() - 1
+    }
+
+    // checked with the state before [tick] and the state after it
+    fn hit(&self, before: &[isize]) -> Option<usize> {
+        self.breakpoints
+            .iter()
+            .position(|&breakpoint| match breakpoint {
+                Breakpoint::Cycle(cycle) => self.cpu.cycle == cycle,
+                Breakpoint::Instruction(index) => {
+                    self.cpu.instruction_index() == index && self.cpu.cycles_done() == 0
+                }
+                Breakpoint::Value(reg, value) => {
+                    let i = REGISTERS.iter().position(|&r| r == reg).unwrap();
+                    self.cpu.reg(reg) == value && before[i] != value
+                }
+            })
+    }
+
+    fn tick(&mut self) -> Option<Stop> {
+        let before: Vec<isize> = REGISTERS.iter().map(|&reg| self.cpu.reg(reg)).collect();
+        if self.cpu.tick().is_none() {
+            if self.cpu.out_of_cycles() {
+                return Some(Stop::Stuck);
+            }
+            return Some(Stop::Halted);
+        }
+        self.hit(&before).map(Stop::Hit)
+    }
+
+    // runs [count] cycles, stopping early for a breakpoint
+    pub fn step_cycles(&mut self, count: usize) -> Stop {
+        for _ in 0..count {
+            if let Some(stop) = self.tick() {
+                return stop;
+            }
+        }
+        Stop::Done
+    }
+
+    // runs until [count] more instructions have finished, stopping early for a breakpoint
+    pub fn step_instructions(&mut self, count: usize) -> Stop {
+        for _ in 0..count {
+            loop {
+                if let Some(stop) = self.tick() {
+                    return stop;
+                }
+                if self.cpu.cycles_done() == 0 {
+                    break;
+                }
+            }
+        }
+        Stop::Done
+    }
+
+    pub fn run(&mut self) -> Stop {
+        loop {
+            if let Some(stop) = self.tick() {
+                return stop;
+            }
+        }
+    }
+
+    // where the program is up to, then the value of every watch
+    pub fn status(&self) -> String {
+        let cpu = &self.cpu;
+        let current = match cpu.current() {
+            Some(instruction) => format!(
+                "{} ({} of {} cycles done)",
+                instruction,
+                cpu.cycles_done(),
+                instruction.cycles()
+            ),
+            None => "halted".to_string(),
+        };
+        let registers: Vec<String> = REGISTERS
+            .iter()
+            .map(|&reg| format!("{}={}", reg.name(), cpu.reg(reg)))
+            .collect();
+        let (bx, by) = self.screen.beam(cpu.cycle);
+        let pixel = if self.screen.sprite_covers(bx, cpu.reg_x()) {
+            "lit"
+        } else {
+            "dark"
+        };
+        let mut lines = vec![format!(
+            "cycle {}, ip {}: {}\n  {}, beam at ({}, {}) drawing {}",
+            cpu.cycle,
+            cpu.instruction_index(),
+            current,
+            registers.join(" "),
+            bx,
+            by,
+            pixel
+        )];
+        for watch in self.watches.iter() {
+            match eval(cpu, &self.screen, watch) {
+                Ok(value) => lines.push(format!("  {} = {}", watch, value)),
+                Err(error) => lines.push(format!("  {}: {}", watch, error)),
+            }
+        }
+        lines.join("\n")
+    }
+}
+
+const HELP: &str = "commands:
+  s [n]                  step n cycles (default 1)
+  n [n]                  step n instructions (default 1)
+  c                      continue until a breakpoint or the end
+  b cycle N | ip N | R N break at a cycle, an instruction, or when register R becomes N
+  d N                    delete breakpoint N
+  w EXPR                 watch an expression, e.g. `w x * cycle`
+  p EXPR                 print an expression once
+  i                      list breakpoints and watches
+  q                      quit";
+
+fn parse_breakpoint(words: &[&str]) -> Option<Breakpoint> {
+    let value = words.get(1)?;
+    match words.first()? {
+        &"cycle" => Some(Breakpoint::Cycle(value.parse().ok()?)),
+        &"ip" => Some(Breakpoint::Instruction(value.parse().ok()?)),
+        name => Some(Breakpoint::Value(
+            Reg::from_name(name)?,
+            value.parse().ok()?,
+        )),
+    }
+}
+
+// reads commands from [input] until it runs out or sees `q`, so tests can pass in a script
+pub fn interactive(
+    debugger: &mut Debugger,
+    input: impl BufRead,
+    mut output: impl Write,
+) -> io::Result<()> {
+    writeln!(output, "{}", HELP)?;
+    writeln!(output, "{}", debugger.status())?;
+    for line in input.lines() {
+        let line = line?;
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let rest = words.get(1..).unwrap_or_default();
+        let count: usize = rest.first().and_then(|word| word.parse().ok()).unwrap_or(1);
+        let stop = match words.first().copied().unwrap_or("s") {
+            "s" | "step" => debugger.step_cycles(count),
+            "n" | "next" => debugger.step_instructions(count),
+            "c" | "continue" => debugger.run(),
+            "b" | "break" => {
+                match parse_breakpoint(rest) {
+                    Some(breakpoint) => {
+                        let index = debugger.add_breakpoint(breakpoint);
+                        writeln!(output, "breakpoint {}: {}", index, breakpoint)?;
+                    }
+                    None => writeln!(
+                        output,
+                        "break needs `cycle N`, `ip N` or a register and value"
+                    )?,
+                }
+                continue;
+            }
+            "d" | "delete" => {
+                match rest.first().and_then(|word| word.parse::<usize>().ok()) {
+                    Some(index) if index < debugger.breakpoints.len() => {
+                        let breakpoint = debugger.breakpoints.remove(index);
+                        writeln!(output, "deleted breakpoint {}: {}", index, breakpoint)?;
+                    }
+                    _ => writeln!(output, "no such breakpoint")?,
+                }
+                continue;
+            }
+            "w" | "watch" | "p" | "print" => {
+                let expr = rest.join(" ");
+                match eval(&debugger.cpu, &debugger.screen, &expr) {
+                    Ok(value) => {
+                        writeln!(output, "{} = {}", expr, value)?;
+                        if words[0].starts_with('w') {
+                            debugger.watches.push(expr);
+                        }
+                    }
+                    Err(error) => writeln!(output, "{}", error)?,
+                }
+                continue;
+            }
+            "i" | "info" => {
+                for (index, breakpoint) in debugger.breakpoints.iter().enumerate() {
+                    writeln!(output, "breakpoint {}: {}", index, breakpoint)?;
+                }
+                for watch in debugger.watches.iter() {
+                    writeln!(output, "watch: {}", watch)?;
+                }
+                continue;
+            }
+            "q" | "quit" => break,
+            _ => {
+                writeln!(output, "{}", HELP)?;
+                continue;
+            }
+        };
+        match stop {
+            Stop::Done => {}
+            Stop::Hit(index) => writeln!(
+                output,
+                "hit breakpoint {}: {}",
+                index, debugger.breakpoints[index]
+            )?,
+            Stop::Halted => writeln!(output, "the program has finished")?,
+            Stop::Stuck => writeln!(output, "{}", debugger.cpu.finished().unwrap_err())?,
+        }
+        writeln!(output, "{}", debugger.status())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Debugger {
+        Debugger::new(
+            CPU::init(include_str!("input-sample.txt")),
+            Screen::default(),
+        )
+    }
+
+    fn script(debugger: &mut Debugger, commands: &str) -> String {
+        let mut output = Vec::new();
+        interactive(debugger, commands.as_bytes(), &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_eval() {
+        let mut debugger = sample();
+        debugger.step_cycles(19);
+        let cpu = &debugger.cpu;
+        assert_eq!(eval(cpu, &Screen::default(), "x * cycle"), Ok(420));
+        assert_eq!(
+            eval(cpu, &Screen::default(), "cycle-1*2+ -x"),
+            Ok(20 - 2 - 21)
+        );
+        assert_eq!(eval(cpu, &Screen::default(), "bx + by"), Ok(19));
+        assert!(eval(cpu, &Screen::default(), "x *").is_err());
+        assert!(eval(cpu, &Screen::default(), "q + 1").is_err());
+    }
+
+    #[test]
+    fn test_eval_wraps() {
+        let program = format!("addx {}\nnoop\nnoop", isize::MAX - 1);
+        let mut debugger = Debugger::new(CPU::init(&program), Screen::default());
+        debugger.watches.push("x * 2 + x".to_string());
+        debugger.step_cycles(2);
+        let screen = Screen::default();
+        assert_eq!(eval(&debugger.cpu, &screen, "x * 2"), Ok(-2));
+        assert_eq!(eval(&debugger.cpu, &screen, "x + 1"), Ok(isize::MIN));
+        assert_eq!(eval(&debugger.cpu, &screen, "-x - 2"), Ok(isize::MAX));
+        assert!(debugger.status().contains(&(isize::MAX - 2).to_string()));
+    }
+
+    #[test]
+    fn test_other_screen() {
+        let screen = Screen {
+            width: 8,
+            height: 2,
+            sprite_width: 1,
+        };
+        let program = format!("noop\naddx 8{}", "\nnoop".repeat(8));
+        let mut debugger = Debugger::new(CPU::init(&program), screen);
+        debugger.step_cycles(3);
+        // cycle 4 is the fourth pixel of the top row, and x has moved away from it
+        assert_eq!(eval(&debugger.cpu, &screen, "bx * 10 + by"), Ok(30));
+        assert!(debugger.status().contains("beam at (3, 0) drawing dark"));
+        // and cycle 9 starts the second row
+        debugger.step_cycles(5);
+        assert_eq!(eval(&debugger.cpu, &screen, "bx * 10 + by"), Ok(1));
+        debugger = Debugger::new(CPU::init("noop"), screen);
+        assert!(debugger.status().contains("beam at (0, 0) drawing dark"));
+        debugger = Debugger::new(CPU::init("noop\nnoop"), screen);
+        debugger.step_cycles(1);
+        assert!(debugger.status().contains("beam at (1, 0) drawing lit"));
+    }
+
+    #[test]
+    fn test_step() {
+        let mut debugger = Debugger::new(CPU::init("noop\naddx 3\naddx -5"), Screen::default());
+        assert_eq!(debugger.step_instructions(2), Stop::Done);
+        assert_eq!(debugger.cpu.cycle, 4);
+        assert_eq!(debugger.cpu.reg_x(), 4);
+        assert_eq!(debugger.step_cycles(1), Stop::Done);
+        assert_eq!(debugger.cpu.cycles_done(), 1);
+        assert_eq!(debugger.step_instructions(5), Stop::Halted);
+        assert_eq!(debugger.cpu.reg_x(), -1);
+    }
+
+    #[test]
+    fn test_stuck() {
+        let mut cpu = CPU::init("spin: jmp spin");
+        cpu.set_cycle_limit(10);
+        let mut debugger = Debugger::new(cpu, Screen::default());
+        assert_eq!(debugger.run(), Stop::Stuck);
+        assert_eq!(debugger.cpu.cycle, 11);
+    }
+
+    #[test]
+    fn test_breakpoints() {
+        let mut debugger = sample();
+        let cycle = debugger.add_breakpoint(Breakpoint::Cycle(20));
+        let ip = debugger.add_breakpoint(Breakpoint::Instruction(30));
+        let value = debugger.add_breakpoint(Breakpoint::Value(Reg::X, 7));
+        assert_eq!(debugger.run(), Stop::Hit(cycle));
+        assert_eq!(debugger.cpu.reg_x(), 21);
+        // x gets to 7 once before instruction 30 and once after it
+        assert_eq!(debugger.run(), Stop::Hit(value));
+        assert_eq!(debugger.cpu.reg_x(), 7);
+        assert_eq!(debugger.run(), Stop::Hit(ip));
+        assert_eq!(debugger.cpu.instruction_index(), 30);
+        assert_eq!(debugger.run(), Stop::Hit(value));
+        assert_eq!(debugger.cpu.reg_x(), 7);
+        debugger.breakpoints.clear();
+        assert_eq!(debugger.run(), Stop::Halted);
+    }
+
+    #[test]
+    fn test_script() {
+        let mut debugger = sample();
+        let output = script(
+            &mut debugger,
+            "b cycle 60\nw x * cycle\nc\nn 2\nd 0\nb q 1\nc\nq\ns",
+        );
+        assert!(output.contains("breakpoint 0: cycle 60\n"));
+        assert!(output.contains("hit breakpoint 0: cycle 60\ncycle 60, ip "));
+        assert!(output.contains("  x * cycle = 1140\n")); // the puzzle says 1140 at cycle 60
+        assert!(output.contains("deleted breakpoint 0: cycle 60\n"));
+        assert!(output.contains("break needs"));
+        assert!(output.contains("the program has finished\ncycle 241, ip 146: halted"));
+        assert_eq!(debugger.cpu.cycle, 241); // nothing after the q ran
+    }
+}
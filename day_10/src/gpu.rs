@@ -1,40 +1,257 @@
+// the puzzle's screen
 const WIDTH: usize = 40;
 const HEIGHT: usize = 6;
+const SPRITE_WIDTH: usize = 3;
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Screen {
+    pub width: usize,
+    pub height: usize,
+    pub sprite_width: usize,
+}
+
+impl Default for Screen {
+    fn default() -> Self {
+        Screen {
+            width: WIDTH,
+            height: HEIGHT,
+            sprite_width: SPRITE_WIDTH,
+        }
+    }
+}
+
+impl Screen {
+    // anything with no pixels has nowhere for the beam to go
+    pub fn new(width: usize, height: usize, sprite_width: usize) -> Result<Self, String> {
+        if width == 0 || height == 0 {
+            return Err(format!(
+                "the screen has to be at least 1x1, not {}x{}",
+                width, height
+            ));
+        }
+        Ok(Screen {
+            width,
+            height,
+            sprite_width,
+        })
+    }
+
+    // where the beam is drawing during [cycle], as (x, y)
+    pub fn beam(&self, cycle: usize) -> (usize, usize) {
+        (
+            (cycle - 1) % self.width,
+            ((cycle - 1) / self.width) % self.height,
+        )
+    }
+
+    // the sprite is centred on [reg_x], an even width sprite has its extra pixel on the right
+    pub fn sprite_covers(&self, x: usize, reg_x: isize) -> bool {
+        let x = x as isize;
+        let left = reg_x - (self.sprite_width as isize - 1) / 2;
+        left <= x && x < left + self.sprite_width as isize
+    }
+}
+
+// one whole screen's worth of pixels
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub index: usize,
+    pub pixels: Vec<Vec<bool>>,
+}
+
+impl Frame {
+    pub fn to_text(&self) -> String {
+        self.pixels
+            .iter()
+            .map(|row| row.iter().map(|&lit| if lit { '#' } else { '.' }).collect())
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    // the frame blown up so that every pixel is [scale] x [scale]
+    fn scaled(&self, scale: usize) -> Vec<Vec<bool>> {
+        self.pixels
+            .iter()
+            .flat_map(|row| {
+                let row: Vec<bool> = row
+                    .iter()
+                    .flat_map(|&lit| std::iter::repeat_n(lit, scale))
+                    .collect();
+                std::iter::repeat_n(row, scale)
+            })
+            .collect()
+    }
+
+    // binary pbm (P4), lit pixels are black like ink on paper
+    pub fn to_pbm(&self, scale: usize) -> Vec<u8> {
+        let pixels = self.scaled(scale);
+        let width = pixels.first().map_or(0, |row| row.len());
+        let mut bytes = format!("P4\n{} {}\n", width, pixels.len()).into_bytes();
+        for row in pixels.iter() {
+            bytes.extend(pack_bits(row));
+        }
+        bytes
+    }
+
+    // a black and white png, lit pixels are white like on the crt
+    pub fn to_png(&self, scale: usize) -> Vec<u8> {
+        crate::png::encode(&[self.scaled(scale)], 0)
+    }
+}
+
+// eight pixels to a byte, most significant bit first, padded out at the end of the row
+pub fn pack_bits(row: &[bool]) -> Vec<u8> {
+    row.chunks(8)
+        .map(|chunk| {
+            chunk
+                .iter()
+                .enumerate()
+                .filter(|&(_, &lit)| lit)
+                .fold(0u8, |byte, (i, _)| byte | (0x80 >> i))
+        })
+        .collect()
+}
+
+// an animated png of [frames], each shown for [delay_ms]. viewers that don't know about
+// animation just show the first frame
+pub fn to_apng(frames: &[Frame], scale: usize, delay_ms: u16) -> Vec<u8> {
+    let images: Vec<Vec<Vec<bool>>> = frames.iter().map(|frame| frame.scaled(scale)).collect();
+    crate::png::encode(&images, delay_ms)
+}
+
+#[allow(clippy::upper_case_acronyms)]
 pub struct GPU {
-    framebuffer: [[bool; WIDTH]; HEIGHT],
+    screen: Screen,
+    framebuffer: Vec<Vec<bool>>,
+    frames: usize,
+    drawn: usize, // pixels drawn since the last frame was finished
 }
+
 impl GPU {
-    pub fn init() -> Self {
+    pub fn with_screen(screen: Screen) -> Self {
+        assert!(
+            screen.width > 0 && screen.height > 0,
+            "the screen can't be empty"
+        );
         GPU {
-            framebuffer: [[false; WIDTH]; HEIGHT],
+            screen,
+            framebuffer: vec![vec![false; screen.width]; screen.height],
+            frames: 0,
+            drawn: 0,
         }
     }
 
-    pub fn display(self: &Self) {
-        println!("");
-        for y in 0..HEIGHT {
-            for x in 0..WIDTH {
-                let lit = self.framebuffer[y][x];
-                if lit {
-                    print!("#");
-                } else {
-                    print!(".");
-                }
-            }
-            println!("");
-        }
-        println!("");
+    pub fn framebuffer(&self) -> &[Vec<bool>] {
+        &self.framebuffer
+    }
+
+    fn frame(&mut self) -> Frame {
+        let frame = Frame {
+            index: self.frames,
+            pixels: self.framebuffer.clone(),
+        };
+        self.frames += 1;
+        self.drawn = 0;
+        frame
+    }
+
+    // draws the pixel for [cycle], and gives back the whole frame once the last pixel is drawn
+    pub fn tick(&mut self, cycle: usize, reg_x: isize) -> Option<Frame> {
+        let (x, y) = self.screen.beam(cycle);
+        self.framebuffer[y][x] = self.screen.sprite_covers(x, reg_x);
+        self.drawn += 1;
+        (x + 1 == self.screen.width && y + 1 == self.screen.height).then(|| self.frame())
     }
 
-    pub fn tick(self: &mut Self, cycle: usize, reg_x: isize) {
-        let x = (cycle - 1) % WIDTH;
-        let y = ((cycle - 1) / WIDTH) % HEIGHT;
-        let xi = x as isize;
-        let lit = reg_x == xi || reg_x == xi + 1 || reg_x == xi - 1;
-        self.framebuffer[y][x] = lit;
-        if x + 1 == WIDTH && y + 1 == HEIGHT {
-            self.display();
+    // the frame that was still being drawn when the program ended, if there is one.
+    // whatever the previous frame had is left in the pixels the beam didn't get to
+    pub fn finish(&mut self) -> Option<Frame> {
+        (self.drawn > 0).then(|| self.frame())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_screen_sizes() {
+        assert_eq!(Screen::new(40, 6, 3), Ok(Screen::default()));
+        assert_eq!(
+            Screen::new(0, 6, 3),
+            Err("the screen has to be at least 1x1, not 0x6".to_string())
+        );
+        assert!(Screen::new(40, 0, 3).is_err());
+        assert!(Screen::new(1, 1, 0).is_ok());
+    }
+
+    #[test]
+    fn test_sprite_widths() {
+        let covered = |sprite_width: usize| -> Vec<usize> {
+            let screen = Screen {
+                sprite_width,
+                ..Screen::default()
+            };
+            (0..10).filter(|&x| screen.sprite_covers(x, 5)).collect()
+        };
+        assert_eq!(covered(3), vec![4, 5, 6]);
+        assert_eq!(covered(1), vec![5]);
+        assert_eq!(covered(4), vec![4, 5, 6, 7]);
+        assert_eq!(covered(0), vec![]);
+    }
+
+    #[test]
+    fn test_frames() {
+        let screen = Screen {
+            width: 4,
+            height: 2,
+            sprite_width: 1,
+        };
+        let mut gpu = GPU::with_screen(screen);
+        let mut frames = Vec::new();
+        for cycle in 1..=11 {
+            let reg_x = if cycle <= 8 {
+                (cycle as isize - 1) % 3
+            } else {
+                0
+            };
+            frames.extend(gpu.tick(cycle, reg_x));
         }
+        frames.extend(gpu.finish());
+        assert_eq!(gpu.finish(), None);
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].to_text(), "###.\n....");
+        assert_eq!(frames[1].index, 1);
+        // the first row was drawn again, the second is left over from the first frame
+        assert_eq!(frames[1].to_text(), "#...\n....");
+    }
+
+    #[test]
+    fn test_pbm() {
+        let frame = Frame {
+            index: 0,
+            pixels: vec![vec![
+                true, false, false, false, false, false, false, false, true,
+            ]],
+        };
+        assert_eq!(frame.to_pbm(1), b"P4\n9 1\n\x80\x80");
+        assert_eq!(frame.to_pbm(2), b"P4\n18 2\n\xc0\x00\xc0\xc0\x00\xc0");
+    }
+
+    #[test]
+    fn test_png() {
+        let frame = Frame {
+            index: 0,
+            pixels: vec![vec![true, false], vec![false, true]],
+        };
+        let png = frame.to_png(1);
+        assert!(png.starts_with(
+            b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR\x00\x00\x00\x02\x00\x00\x00\x02\x01\x00"
+        ));
+        assert!(png.ends_with(b"IEND\xae\x42\x60\x82"));
+        let apng = to_apng(&[frame.clone(), frame], 1, 500);
+        assert!(apng.windows(4).any(|w| w == b"acTL"));
+        assert_eq!(apng.windows(4).filter(|w| w == b"fcTL").count(), 2);
+        assert_eq!(apng.windows(4).filter(|w| w == b"fdAT").count(), 1);
     }
 }
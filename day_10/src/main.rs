@@ -1,23 +1,199 @@
+mod asm;
 mod cpu;
+mod debugger;
 mod gpu;
+mod ocr;
+mod png;
+mod synth;
+mod trace;
 
-fn main() {
-    let input = include_str!("input.txt");
-    let mut cpu = cpu::CPU::init(input);
-    // println!(
-    //     "cumulative signal stregnth: {}",
-    //     cpu.get_cumulative_signal_stregnths()
-    // );
-    let mut gpu = gpu::GPU::init();
-    while {
-        gpu.tick(cpu.cycle, cpu.reg_x);
-        cpu.tick() == Some(())
-    } {}
-    // for _ in 0..3 {
-    //     print!("cycle {} (reg_x is {})", cpu.cycle, cpu.reg_x);
-    //     gpu.tick(cpu.cycle, cpu.reg_x);
-    //     gpu.display();
+// pulls `--name value` pairs out of [args], leaving the rest in order
+fn take_options(args: &mut Vec<String>) -> Vec<(String, String)> {
+    let mut options = Vec::new();
+    while let Some(i) = args.iter().position(|arg| arg.starts_with("--")) {
+        let name = args.remove(i);
+        let value = if i < args.len() {
+            args.remove(i)
+        } else {
+            panic!("{} needs a value", name)
+        };
+        options.push((name, value));
+    }
+    options
+}
 
-    //     cpu.tick();
-    // }
+fn option(options: &[(String, String)], name: &str, default: usize) -> usize {
+    options
+        .iter()
+        .rev()
+        .find(|(option, _)| option == name)
+        .map_or(default, |(_, value)| {
+            value
+                .parse()
+                .unwrap_or_else(|_| panic!("{} must be a number", name))
+        })
+}
+
+fn main() {
+    // programs can use comments, labels, macros and repeat blocks, see asm.rs.
+    // `cargo run -- run <file>` runs your own program instead of the puzzle input,
+    // `cargo run -- disasm [file]` prints it back out without the macros and repeats,
+    // `cargo run -- debug [file]` steps through it with commands from stdin,
+    // `cargo run -- trace <csv|json> [file]` prints what happened every cycle,
+    // `cargo run -- strength <cycles> [file]` adds up the signal strength at other cycles, e.g. `20+40` or `20,60`, and
+    // `cargo run -- frames <dir> [file]` saves every frame as pbm and png, plus an animated png of all of them, and
+    // `cargo run -- synth <text|image> <TEXT|file>` writes a program that draws some letters or a # and . picture.
+    // `--cycles` sets how long a program can run before it's assumed to be stuck,
+    // `--width`, `--height` and `--sprite` change the screen, `--scale` and `--delay` (ms) change the images
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let options = take_options(&mut args);
+    let default_screen = gpu::Screen::default();
+    let screen = gpu::Screen::new(
+        option(&options, "--width", default_screen.width),
+        option(&options, "--height", default_screen.height),
+        option(&options, "--sprite", default_screen.sprite_width),
+    );
+    let screen = match screen {
+        Ok(screen) => screen,
+        Err(error) => {
+            println!("{}", error);
+            return;
+        }
+    };
+    let command = args.first().map(String::as_str);
+    // the program to run comes after the command and its argument, if there is one
+    let path = match command {
+        Some("run" | "debug" | "disasm") => args.get(1),
+        Some("trace" | "strength" | "frames") => args.get(2),
+        _ => None,
+    };
+    let program;
+    let input = match path {
+        Some(path) => {
+            program = std::fs::read_to_string(path).expect("failed to read program");
+            &program
+        }
+        None if command == Some("run") => panic!("run needs a program to run"),
+        None => include_str!("input.txt"),
+    };
+    let limit = option(&options, "--cycles", cpu::CYCLE_LIMIT);
+    let load = || {
+        let mut cpu = cpu::CPU::init(input);
+        cpu.set_cycle_limit(limit);
+        cpu
+    };
+    match command {
+        Some("synth") => {
+            let what = args
+                .get(2)
+                .expect("synth needs some text or a picture to draw");
+            let target = match args.get(1).map(String::as_str) {
+                Some("text") => synth::text_image(what, screen),
+                Some("image") => Ok(synth::parse_image(
+                    &std::fs::read_to_string(what).expect("failed to read picture"),
+                )),
+                _ => panic!("synth draws either text or an image"),
+            };
+            match target.and_then(|target| synth::synthesize(&target, screen)) {
+                Ok(synthesis) => {
+                    println!("{}", asm::disassemble(&synthesis.program));
+                    // as comments, so the output can still be run as it is
+                    for (x, y) in synthesis.unreachable {
+                        println!("# can't draw the pixel at {}, {}", x, y);
+                    }
+                }
+                Err(error) => println!("{}", error),
+            }
+            return;
+        }
+        Some("disasm") => {
+            match asm::assemble(input) {
+                Ok(instructions) => println!("{}", asm::disassemble(&instructions)),
+                Err(error) => println!("{}", error),
+            }
+            return;
+        }
+        Some("debug") => {
+            let mut debugger = debugger::Debugger::new(load(), screen);
+            debugger::interactive(&mut debugger, std::io::stdin().lock(), std::io::stdout())
+                .expect("failed to talk to the terminal");
+            return;
+        }
+        Some("trace") => {
+            let mut cpu = load();
+            let rows = trace::Trace::with_screen(&mut cpu, screen);
+            match args.get(1).map(String::as_str) {
+                Some("json") => println!("{}", trace::to_json(rows)),
+                _ => println!("{}", trace::to_csv(rows)),
+            }
+            if let Err(error) = cpu.finished() {
+                eprintln!("{}", error);
+            }
+            return;
+        }
+        Some("strength") => {
+            let samples = args
+                .get(1)
+                .and_then(|text| trace::Samples::parse(text))
+                .expect("strength needs cycles like `20+40` or `20,60,100`");
+            let mut cpu = load();
+            let sum = trace::signal_strength_sum(trace::Trace::new(&mut cpu), &samples);
+            match cpu.finished() {
+                Ok(()) => println!("signal strength: {}", sum),
+                Err(error) => println!("{}", error),
+            }
+            return;
+        }
+        _ => {}
+    }
+    match load().get_cumulative_signal_stregnths() {
+        Ok(strength) => println!("cumulative signal stregnth: {}", strength),
+        Err(error) => println!("{}", error),
+    }
+    let mut cpu = load();
+    let mut gpu = gpu::GPU::with_screen(screen);
+    let mut frames = Vec::new();
+    // only draw while there's an instruction running, otherwise the last tick wraps around
+    // and draws over the first pixel again
+    while cpu.current().is_some() {
+        frames.extend(gpu.tick(cpu.cycle, cpu.reg_x()));
+        cpu.tick();
+    }
+    frames.extend(gpu.finish());
+    if let Err(error) = cpu.finished() {
+        println!("{}", error);
+    }
+    if command == Some("frames") {
+        let dir = std::path::Path::new(args.get(1).expect("frames needs a directory to write to"));
+        let scale = option(&options, "--scale", 8);
+        let delay = u16::try_from(option(&options, "--delay", 500))
+            .unwrap_or_else(|_| panic!("--delay can be at most {} ms", u16::MAX));
+        let write = |name: String, bytes: Vec<u8>| {
+            std::fs::write(dir.join(&name), bytes).expect("failed to write image");
+            println!("wrote {}", dir.join(name).display());
+        };
+        for frame in frames.iter() {
+            write(format!("frame_{:03}.pbm", frame.index), frame.to_pbm(scale));
+            write(format!("frame_{:03}.png", frame.index), frame.to_png(scale));
+        }
+        if !frames.is_empty() {
+            write(
+                "animation.png".to_string(),
+                gpu::to_apng(&frames, scale, delay),
+            );
+        }
+        return;
+    }
+    for frame in frames.iter() {
+        println!("\nframe {}:\n{}\n", frame.index, frame.to_text());
+    }
+    match ocr::recognize(gpu.framebuffer()) {
+        Ok(letters) => println!("letters on the screen: {}", letters),
+        Err(error) => println!("{}", error),
+    }
+    let registers: Vec<String> = cpu::REGISTERS
+        .iter()
+        .map(|&reg| format!("{}={}", reg.name(), cpu.reg(reg)))
+        .collect();
+    println!("halted at cycle {} with {}", cpu.cycle, registers.join(" "));
 }
@@ -0,0 +1,217 @@
+use std::fmt;
+
+pub const GLYPH_WIDTH: usize = 4;
+pub const GLYPH_HEIGHT: usize = 6;
+// every letter gets a blank column after it
+pub const CELL_WIDTH: usize = GLYPH_WIDTH + 1;
+
+// the letters that turn up in the puzzles. the font has a Y too but it's 5 pixels wide,
+// so it can't be one of 8 letters on a 40 pixel screen
+pub const FONT: [(char, [&str; GLYPH_HEIGHT]); 17] = [
+    ('A', [".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', ["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', [".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', ["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', ["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', [".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', ["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', [".###", "..#.", "..#.", "..#.", "..#.", ".###"]),
+    ('J', ["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', ["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', ["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', [".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', ["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', ["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', [".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', ["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('Z', ["####", "...#", "..#.", ".#..", "#...", "####"]),
+];
+
+pub fn glyph(letter: char) -> Option<[&'static str; GLYPH_HEIGHT]> {
+    FONT.iter()
+        .find(|(c, _)| *c == letter.to_ascii_uppercase())
+        .map(|(_, rows)| *rows)
+}
+
+// [text] written out in the font, each letter followed by a blank column. a space is a blank cell,
+// anything else that isn't in the font is given back as the error
+pub fn render(text: &str) -> Result<Vec<Vec<bool>>, char> {
+    let glyphs = text
+        .chars()
+        .map(|c| match c {
+            ' ' => Ok(["...."; GLYPH_HEIGHT]),
+            _ => glyph(c).ok_or(c),
+        })
+        .collect::<Result<Vec<_>, char>>()?;
+    Ok((0..GLYPH_HEIGHT)
+        .map(|y| {
+            glyphs
+                .iter()
+                .flat_map(|rows| rows[y].chars().map(|p| p == '#').chain([false]))
+                .collect()
+        })
+        .collect())
+}
+
+#[derive(Debug, PartialEq)]
+pub enum OcrError {
+    // the screen has to be exactly one letter tall
+    Height(usize),
+    // the cells that didn't look like any letter, with what they looked like.
+    // [text] has a ? in place of each one
+    Unknown {
+        text: String,
+        glyphs: Vec<(usize, [String; GLYPH_HEIGHT])>,
+    },
+    // the column between two letters has something lit in it, so they don't line up with the cells
+    Separator(usize),
+}
+
+impl fmt::Display for OcrError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OcrError::Height(height) => write!(
+                f,
+                "the screen is {} pixels tall but letters are {}",
+                height, GLYPH_HEIGHT
+            ),
+            OcrError::Separator(x) => {
+                write!(f, "column {} should be blank between letters but isn't", x)
+            }
+            OcrError::Unknown { text, glyphs } => {
+                write!(f, "couldn't read all of {}", text)?;
+                for (index, rows) in glyphs {
+                    write!(f, "\nletter {}:", index)?;
+                    for row in rows {
+                        write!(f, "\n  {}", row)?;
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+// reads the letters off a screen of lit pixels, a blank cell is read as a space
+pub fn recognize<R: AsRef<[bool]>>(screen: &[R]) -> Result<String, OcrError> {
+    if screen.len() != GLYPH_HEIGHT {
+        return Err(OcrError::Height(screen.len()));
+    }
+    let width = screen
+        .iter()
+        .map(|row| row.as_ref().len())
+        .min()
+        .unwrap_or(0);
+    let gap = (GLYPH_WIDTH..width)
+        .step_by(CELL_WIDTH)
+        .find(|&x| screen.iter().any(|row| row.as_ref()[x]));
+    if let Some(x) = gap {
+        return Err(OcrError::Separator(x));
+    }
+    let mut text = String::new();
+    let mut unknown = Vec::new();
+    for (index, start) in (0..width).step_by(CELL_WIDTH).enumerate() {
+        let rows: [String; GLYPH_HEIGHT] = std::array::from_fn(|y| {
+            (start..start + GLYPH_WIDTH)
+                .map(|x| match screen[y].as_ref().get(x) {
+                    Some(true) => '#',
+                    _ => '.',
+                })
+                .collect()
+        });
+        if rows.iter().all(|row| !row.contains('#')) {
+            text.push(' ');
+            continue;
+        }
+        match FONT.iter().find(|(_, glyph)| *glyph == rows) {
+            Some((letter, _)) => text.push(*letter),
+            None => {
+                text.push('?');
+                unknown.push((index, rows));
+            }
+        }
+    }
+    if unknown.is_empty() {
+        Ok(text)
+    } else {
+        Err(OcrError::Unknown {
+            text,
+            glyphs: unknown,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::CPU;
+    use crate::trace::Trace;
+
+    fn screen_from(program: &str) -> Vec<Vec<bool>> {
+        let mut screen = vec![vec![false; 40]; 6];
+        for row in Trace::new(&mut CPU::init(program)) {
+            let (x, y) = row.pixel;
+            screen[y][x] = row.lit;
+        }
+        screen
+    }
+
+    #[test]
+    fn test_input() {
+        assert_eq!(
+            recognize(&screen_from(include_str!("input.txt"))),
+            Ok("RKPJBPLA".to_string())
+        );
+    }
+
+    #[test]
+    fn test_whole_font() {
+        let letters: String = FONT.iter().map(|(c, _)| c).collect();
+        assert_eq!(recognize(&render(&letters).unwrap()), Ok(letters));
+        assert_eq!(recognize(&render("hi z").unwrap()), Ok("HI Z".to_string()));
+        assert_eq!(render("hey"), Err('y'));
+    }
+
+    #[test]
+    fn test_unknown_glyphs() {
+        // the sample draws stripes instead of letters, right across the gaps
+        let mut screen = screen_from(include_str!("input-sample.txt"));
+        assert_eq!(recognize(&screen), Err(OcrError::Separator(4)));
+        // with the gaps cleared the stripes still don't look like anything
+        for row in screen.iter_mut() {
+            for x in (GLYPH_WIDTH..row.len()).step_by(CELL_WIDTH) {
+                row[x] = false;
+            }
+        }
+        let error = recognize(&screen).unwrap_err();
+        let OcrError::Unknown { text, glyphs } = &error else {
+            panic!("expected unknown glyphs, got {:?}", error);
+        };
+        assert_eq!(text, "????????");
+        assert_eq!(glyphs.len(), 8);
+        assert_eq!(glyphs[0].1[0], "##..");
+        assert!(error
+            .to_string()
+            .starts_with("couldn't read all of ????????\nletter 0:\n  ##..\n"));
+    }
+
+    #[test]
+    fn test_lit_separator() {
+        let mut screen = render("HI").unwrap();
+        screen[2][4] = true; // joins up the H and the I
+        let error = recognize(&screen).unwrap_err();
+        assert_eq!(error, OcrError::Separator(4));
+        assert_eq!(
+            error.to_string(),
+            "column 4 should be blank between letters but isn't"
+        );
+        screen[2][4] = false;
+        screen[0][9] = true;
+        assert_eq!(recognize(&screen), Err(OcrError::Separator(9)));
+    }
+
+    #[test]
+    fn test_wrong_height() {
+        assert_eq!(recognize(&[[true; 40]; 5]), Err(OcrError::Height(5)));
+    }
+}
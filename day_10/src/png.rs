@@ -0,0 +1,126 @@
+use crate::gpu::pack_bits;
+
+// just enough png to write black and white images without pulling in a crate:
+// 1 bit greyscale, with the image data in uncompressed deflate blocks
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb88320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in bytes {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x01];
+    let mut blocks = data.chunks(0xffff).peekable();
+    if blocks.peek().is_none() {
+        out.extend([1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let len = block.len() as u16;
+        out.push(last as u8);
+        out.extend(len.to_le_bytes());
+        out.extend((!len).to_le_bytes());
+        out.extend(block);
+    }
+    out.extend(adler32(data).to_be_bytes());
+    out
+}
+
+fn chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend((data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend(kind);
+    out.extend(data);
+    let crc = crc32(&out[start..]);
+    out.extend(crc.to_be_bytes());
+}
+
+// every scanline starts with filter type 0 (none)
+fn image_data(image: &[Vec<bool>]) -> Vec<u8> {
+    let mut raw = Vec::new();
+    for row in image {
+        raw.push(0);
+        raw.extend(pack_bits(row));
+    }
+    zlib_stored(&raw)
+}
+
+// a plain png for one image, an apng when there's more than one
+pub fn encode(images: &[Vec<Vec<bool>>], delay_ms: u16) -> Vec<u8> {
+    let first = images.first().expect("need at least one image");
+    let width = first.first().map_or(0, |row| row.len()) as u32;
+    let height = first.len() as u32;
+    let mut out = b"\x89PNG\r\n\x1a\n".to_vec();
+    let mut header = Vec::new();
+    header.extend(width.to_be_bytes());
+    header.extend(height.to_be_bytes());
+    header.extend([1, 0, 0, 0, 0]); // 1 bit greyscale, no interlacing
+    chunk(&mut out, b"IHDR", &header);
+    let animated = images.len() > 1;
+    if animated {
+        let mut control = (images.len() as u32).to_be_bytes().to_vec();
+        control.extend(0u32.to_be_bytes()); // loop forever
+        chunk(&mut out, b"acTL", &control);
+    }
+    let mut sequence = 0u32;
+    for (i, image) in images.iter().enumerate() {
+        if animated {
+            let mut control = sequence.to_be_bytes().to_vec();
+            control.extend(width.to_be_bytes());
+            control.extend(height.to_be_bytes());
+            control.extend([0; 8]); // no offset
+            control.extend(delay_ms.to_be_bytes());
+            control.extend(1000u16.to_be_bytes());
+            control.extend([0, 0]); // no disposal or blending, each frame covers the last
+            chunk(&mut out, b"fcTL", &control);
+            sequence += 1;
+        }
+        if i == 0 {
+            chunk(&mut out, b"IDAT", &image_data(image));
+        } else {
+            let mut data = sequence.to_be_bytes().to_vec();
+            data.extend(image_data(image));
+            chunk(&mut out, b"fdAT", &data);
+            sequence += 1;
+        }
+    }
+    chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checksums() {
+        assert_eq!(crc32(b"IEND"), 0xae426082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e60398);
+    }
+
+    #[test]
+    fn test_zlib_stored() {
+        assert_eq!(
+            zlib_stored(b"ab"),
+            vec![0x78, 0x01, 1, 2, 0, 0xfd, 0xff, b'a', b'b', 1, 0x26, 0, 0xc4]
+        );
+    }
+}
@@ -0,0 +1,278 @@
+use crate::cpu::{Instruction, Reg};
+use crate::gpu::Screen;
+use crate::ocr;
+
+// works backwards from a picture to a program that draws it.
+// the only thing a program controls is x during each cycle, and x can only change when an addx
+// finishes, so it has to stay put for at least two cycles before every change, and it starts at 1.
+// within that, the search finds the x for every cycle that gets the most pixels right,
+// then writes it out as noops with an addx at the end of each stretch
+
+#[derive(Debug, PartialEq)]
+pub struct Synthesis {
+    pub program: Vec<Instruction>,
+    // the pixels that even the best program gets wrong, as (x, y)
+    pub unreachable: Vec<(usize, usize)>,
+}
+
+// pixels wrong, then how many times x changes so that the program stays simple
+type Cost = (usize, usize);
+
+// the cheapest way to reach every value in one cycle, without and with it being settled,
+// which is whether x has already had the value for a cycle and so an addx can end after this one
+type Layer = Vec<[Option<Cost>; 2]>;
+
+fn cheaper(cost: Cost, than: Option<Cost>) -> bool {
+    than.is_none_or(|than| cost < than)
+}
+
+pub fn synthesize(target: &[Vec<bool>], screen: Screen) -> Result<Synthesis, String> {
+    if screen.width == 0 || screen.height == 0 {
+        return Err("there's nothing to draw on a screen with no pixels".to_string());
+    }
+    if target.len() != screen.height || target.iter().any(|row| row.len() != screen.width) {
+        return Err(format!(
+            "the image has to be {}x{} to fit the screen",
+            screen.width, screen.height
+        ));
+    }
+    // anything further out than this can't touch the screen, so it's no different to the ends
+    let sprite = screen.sprite_width as isize;
+    let values: Vec<isize> = (-sprite - 1..=screen.width as isize + sprite).collect();
+    let wrong = |cycle: usize, x: isize| -> usize {
+        let (bx, by) = screen.beam(cycle);
+        (screen.sprite_covers(bx, x) != target[by][bx]) as usize
+    };
+
+    // only the last cycle's costs are kept, plus enough to find the way back. a value that stays put
+    // came from the same value, so that's one bit for whether it was settled then. a value that's
+    // just changed came from the cheapest settled value, or the runner up if that's the same one,
+    // so that's another bit, with the two of them kept for each cycle
+    let cycles = screen.width * screen.height;
+    let mut came_from = vec![0u8; cycles * values.len()];
+    let mut cheapest: Vec<[Option<usize>; 2]> = vec![[None; 2]; cycles];
+    let mut layer: Layer = vec![[None; 2]; values.len()];
+    let start = values.iter().position(|&x| x == 1).unwrap();
+    layer[start][0] = Some((wrong(1, 1), 0));
+    for cycle in 1..cycles {
+        let mut best: [Option<usize>; 2] = [None; 2];
+        for value in 0..values.len() {
+            let Some(cost) = layer[value][1] else {
+                continue;
+            };
+            if cheaper(cost, best[0].and_then(|b| layer[b][1])) {
+                best = [Some(value), best[0]];
+            } else if cheaper(cost, best[1].and_then(|b| layer[b][1])) {
+                best[1] = Some(value);
+            }
+        }
+        cheapest[cycle] = best;
+
+        let mut next: Layer = vec![[None; 2]; values.len()];
+        for to in 0..values.len() {
+            let pixel = wrong(cycle + 1, values[to]);
+            let mut bits = 0;
+            for settled in [false, true] {
+                if let Some((pixels, changes)) = layer[to][settled as usize] {
+                    let cost = (pixels + pixel, changes);
+                    if cheaper(cost, next[to][1]) {
+                        next[to][1] = Some(cost);
+                        bits = (settled as u8) << 1;
+                    }
+                }
+            }
+            let runner_up = best[0] == Some(to);
+            if let Some(from) = best[runner_up as usize] {
+                let (pixels, changes) = layer[from][1].unwrap();
+                next[to][0] = Some((pixels + pixel, changes + 1));
+                bits |= runner_up as u8;
+            }
+            came_from[cycle * values.len() + to] = bits;
+        }
+        layer = next;
+    }
+
+    let mut state = (0..values.len())
+        .flat_map(|value| [(value, false), (value, true)])
+        .filter_map(|(value, settled)| Some((layer[value][settled as usize]?, value, settled)))
+        .min()
+        .map(|(_, value, settled)| (value, settled))
+        .unwrap();
+    let mut xs = vec![0; cycles];
+    for cycle in (1..cycles).rev() {
+        xs[cycle] = values[state.0];
+        let bits = came_from[cycle * values.len() + state.0];
+        state = if state.1 {
+            (state.0, bits & 2 != 0)
+        } else {
+            (cheapest[cycle][(bits & 1) as usize].unwrap(), true)
+        };
+    }
+    xs[0] = values[state.0];
+
+    let unreachable = (1..=cycles)
+        .filter(|&cycle| wrong(cycle, xs[cycle - 1]) > 0)
+        .map(|cycle| screen.beam(cycle))
+        .collect();
+    Ok(Synthesis {
+        program: program_for(&xs),
+        unreachable,
+    })
+}
+
+// noops to hold x where it is and an addx to move it on, the search made sure there's room for it
+fn program_for(xs: &[isize]) -> Vec<Instruction> {
+    let mut program = Vec::new();
+    let mut start = 0;
+    for end in 1..=xs.len() {
+        if end < xs.len() && xs[end] == xs[start] {
+            continue;
+        }
+        let length = end - start;
+        if end < xs.len() {
+            program.extend(std::iter::repeat_n(Instruction::Noop, length - 2));
+            program.push(Instruction::Add(Reg::X, xs[end] - xs[start]));
+        } else {
+            program.extend(std::iter::repeat_n(Instruction::Noop, length));
+        }
+        start = end;
+    }
+    program
+}
+
+// [text] in the puzzle's font, starting at the left of the screen
+pub fn text_image(text: &str, screen: Screen) -> Result<Vec<Vec<bool>>, String> {
+    if screen.height != ocr::GLYPH_HEIGHT {
+        return Err(format!(
+            "letters are {} pixels tall but the screen is {}",
+            ocr::GLYPH_HEIGHT,
+            screen.height
+        ));
+    }
+    let mut image =
+        ocr::render(text).map_err(|c| format!("there's no {} in the font", c.escape_debug()))?;
+    for row in image.iter_mut() {
+        // the blank column after the last letter is allowed to fall off the edge
+        if row[screen.width.min(row.len())..].contains(&true) {
+            return Err(format!("{} doesn't fit on the screen", text));
+        }
+        row.resize(screen.width, false);
+    }
+    Ok(image)
+}
+
+// a picture drawn with # for lit pixels and anything else for dark ones, like the frames are printed
+pub fn parse_image(text: &str) -> Vec<Vec<bool>> {
+    text.lines()
+        .map(|line| line.trim_end_matches('\r'))
+        .filter(|line| !line.is_empty())
+        .map(|line| line.chars().map(|c| c == '#').collect())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::CPU;
+    use crate::gpu::GPU;
+
+    fn draw(program: &[Instruction], screen: Screen) -> Vec<Vec<bool>> {
+        let mut cpu = CPU::load(program.to_vec());
+        let mut gpu = GPU::with_screen(screen);
+        while cpu.current().is_some() {
+            gpu.tick(cpu.cycle, cpu.reg_x());
+            cpu.tick();
+        }
+        assert_eq!(cpu.cycle - 1, screen.width * screen.height);
+        gpu.framebuffer().to_vec()
+    }
+
+    #[test]
+    fn test_text() {
+        let screen = Screen::default();
+        let target = text_image("FIREBALL", screen).unwrap();
+        let synthesis = synthesize(&target, screen).unwrap();
+        assert_eq!(synthesis.unreachable, vec![]);
+        let drawn = draw(&synthesis.program, screen);
+        assert_eq!(drawn, target);
+        assert_eq!(ocr::recognize(&drawn), Ok("FIREBALL".to_string()));
+        // the gap in the top of the H would need x to move before it's allowed to
+        let target = text_image("HELLO", screen).unwrap();
+        assert_eq!(
+            synthesize(&target, screen).unwrap().unreachable,
+            vec![(1, 0)]
+        );
+    }
+
+    #[test]
+    fn test_puzzle_screen() {
+        // whatever the puzzle input draws can obviously be drawn
+        let screen = Screen::default();
+        let program = crate::asm::assemble(include_str!("input.txt")).unwrap();
+        let target = draw(&program, screen);
+        let synthesis = synthesize(&target, screen).unwrap();
+        assert_eq!(synthesis.unreachable, vec![]);
+        assert_eq!(draw(&synthesis.program, screen), target);
+    }
+
+    #[test]
+    fn test_unreachable() {
+        // x is 1 for the first two cycles whatever the program does
+        let screen = Screen::default();
+        let dark = vec![vec![false; 40]; 6];
+        let synthesis = synthesize(&dark, screen).unwrap();
+        assert_eq!(synthesis.unreachable, vec![(0, 0), (1, 0)]);
+        let drawn = draw(&synthesis.program, screen);
+        let lit: usize = drawn.iter().flatten().filter(|&&lit| lit).count();
+        assert_eq!(lit, 2);
+        // with a one pixel sprite, x would have to follow the beam every cycle to draw a line,
+        // but it can only move every other one
+        let screen = Screen {
+            width: 8,
+            height: 1,
+            sprite_width: 1,
+        };
+        let synthesis = synthesize(&parse_image(".#..###."), screen).unwrap();
+        assert_eq!(synthesis.unreachable, vec![(6, 0)]);
+        let synthesis = synthesize(&parse_image(".#..##.."), screen).unwrap();
+        assert_eq!(synthesis.unreachable, vec![]);
+    }
+
+    #[test]
+    fn test_other_screen() {
+        let screen = Screen {
+            width: 6,
+            height: 2,
+            sprite_width: 1,
+        };
+        let target = parse_image("#.....\n#.#..#\n");
+        // only line endings are stripped, a leading space is a dark pixel
+        assert_eq!(
+            parse_image(" #\r\n#.\r\n"),
+            vec![vec![false, true], vec![true, false]]
+        );
+        let synthesis = synthesize(&target, screen).unwrap();
+        assert_eq!(synthesis.unreachable, vec![(0, 0), (1, 0)]);
+        assert_eq!(draw(&synthesis.program, screen)[1], target[1]);
+    }
+
+    #[test]
+    fn test_bad_targets() {
+        let screen = Screen::default();
+        assert!(synthesize(&[vec![true; 40]], screen).is_err());
+        for (width, height) in [(0, 6), (40, 0)] {
+            let screen = Screen {
+                width,
+                height,
+                sprite_width: 3,
+            };
+            assert!(synthesize(&vec![vec![]; height], screen).is_err());
+        }
+        assert_eq!(
+            text_image("HI?", screen),
+            Err("there's no ? in the font".to_string())
+        );
+        assert!(text_image("ABCEFGHI", screen).is_ok());
+        assert!(text_image("ABCEFGHIJ", screen).is_err());
+    }
+}
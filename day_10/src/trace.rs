@@ -0,0 +1,266 @@
+use crate::cpu::{Instruction, CPU};
+use crate::gpu::Screen;
+
+// everything that happened during one cycle
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Row {
+    pub cycle: usize,
+    pub ip: usize,
+    pub instruction: Instruction,
+    pub x_during: isize,
+    pub x_after: isize,
+    pub pixel: (usize, usize),
+    pub lit: bool,
+}
+
+impl Row {
+    // wraps around like the registers do
+    pub fn signal_strength(&self) -> isize {
+        (self.cycle as isize).wrapping_mul(self.x_during)
+    }
+}
+
+// runs [cpu] one cycle at a time, giving a row for each until the program ends
+pub struct Trace<'a> {
+    cpu: &'a mut CPU,
+    screen: Screen,
+}
+
+impl<'a> Trace<'a> {
+    pub fn new(cpu: &'a mut CPU) -> Self {
+        Trace::with_screen(cpu, Screen::default())
+    }
+
+    // works out the pixels for a different shaped screen
+    pub fn with_screen(cpu: &'a mut CPU, screen: Screen) -> Self {
+        Trace { cpu, screen }
+    }
+}
+
+impl Iterator for Trace<'_> {
+    type Item = Row;
+
+    fn next(&mut self) -> Option<Row> {
+        let cycle = self.cpu.cycle;
+        let ip = self.cpu.instruction_index();
+        let instruction = self.cpu.current()?;
+        let x_during = self.cpu.reg_x();
+        let pixel = self.screen.beam(cycle);
+        self.cpu.tick()?;
+        Some(Row {
+            cycle,
+            ip,
+            instruction,
+            x_during,
+            x_after: self.cpu.reg_x(),
+            pixel,
+            lit: self.screen.sprite_covers(pixel.0, x_during),
+        })
+    }
+}
+
+// which cycles count towards the signal strength
+#[derive(Debug, Clone, PartialEq)]
+pub enum Samples {
+    Every { first: usize, period: usize },
+    At(Vec<usize>),
+}
+
+impl Samples {
+    // 20th, 60th, 100th, ... like the puzzle
+    pub const PUZZLE: Samples = Samples::Every {
+        first: 20,
+        period: 40,
+    };
+
+    // `20+40` for every 40 cycles starting at 20, or a list like `20,60,100`
+    pub fn parse(text: &str) -> Option<Samples> {
+        if let Some((first, period)) = text.split_once('+') {
+            let period = period.trim().parse().ok()?;
+            if period == 0 {
+                return None;
+            }
+            return Some(Samples::Every {
+                first: first.trim().parse().ok()?,
+                period,
+            });
+        }
+        let cycles = text
+            .split(',')
+            .map(|cycle| cycle.trim().parse().ok())
+            .collect::<Option<Vec<usize>>>()?;
+        Some(Samples::At(cycles))
+    }
+
+    pub fn contains(&self, cycle: usize) -> bool {
+        match self {
+            Samples::Every { first, period } => {
+                cycle >= *first && (cycle - first).is_multiple_of(*period)
+            }
+            Samples::At(cycles) => cycles.contains(&cycle),
+        }
+    }
+}
+
+pub fn signal_strength_sum(rows: impl IntoIterator<Item = Row>, samples: &Samples) -> isize {
+    rows.into_iter()
+        .filter(|row| samples.contains(row.cycle))
+        .map(|row| row.signal_strength())
+        .fold(0, isize::wrapping_add)
+}
+
+pub fn to_csv(rows: impl IntoIterator<Item = Row>) -> String {
+    let mut lines = vec![
+        "cycle,ip,instruction,x_during,x_after,signal_strength,pixel_x,pixel_y,lit".to_string(),
+    ];
+    for row in rows {
+        lines.push(format!(
+            "{},{},{},{},{},{},{},{},{}",
+            row.cycle,
+            row.ip,
+            row.instruction,
+            row.x_during,
+            row.x_after,
+            row.signal_strength(),
+            row.pixel.0,
+            row.pixel.1,
+            row.lit
+        ));
+    }
+    lines.join("\n")
+}
+
+// an array with one object per cycle, one per line so it still diffs nicely
+pub fn to_json(rows: impl IntoIterator<Item = Row>) -> String {
+    let objects: Vec<String> = rows
+        .into_iter()
+        .map(|row| {
+            format!(
+                "  {{\"cycle\": {}, \"ip\": {}, \"instruction\": \"{}\", \"x_during\": {}, \"x_after\": {}, \"signal_strength\": {}, \"pixel\": [{}, {}], \"lit\": {}}}",
+                row.cycle,
+                row.ip,
+                row.instruction,
+                row.x_during,
+                row.x_after,
+                row.signal_strength(),
+                row.pixel.0,
+                row.pixel.1,
+                row.lit
+            )
+        })
+        .collect();
+    if objects.is_empty() {
+        return "[]".to_string();
+    }
+    format!("[\n{}\n]", objects.join(",\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_rows() -> Vec<Row> {
+        Trace::new(&mut CPU::init(include_str!("input-sample.txt"))).collect()
+    }
+
+    #[test]
+    fn test_trace() {
+        let rows: Vec<Row> = Trace::new(&mut CPU::init("noop\naddx 3\naddx -5")).collect();
+        assert_eq!(rows.len(), 5);
+        let during: Vec<isize> = rows.iter().map(|row| row.x_during).collect();
+        let after: Vec<isize> = rows.iter().map(|row| row.x_after).collect();
+        assert_eq!(during, vec![1, 1, 1, 4, 4]);
+        assert_eq!(after, vec![1, 1, 4, 4, -1]);
+        assert_eq!(rows[2].ip, 1);
+        assert_eq!(rows[4].pixel, (4, 0));
+        assert!(rows[4].lit); // x is 4 while the fifth pixel is drawn
+        let rows: Vec<Row> = Trace::new(&mut CPU::init("addx 9\nnoop")).collect();
+        assert!(!rows[2].lit); // the sprite has moved away to 10
+    }
+
+    #[test]
+    fn test_other_screen() {
+        let screen = Screen {
+            width: 3,
+            height: 2,
+            sprite_width: 1,
+        };
+        let rows: Vec<Row> =
+            Trace::with_screen(&mut CPU::init("noop\naddx 1\naddx 1\nnoop\nnoop"), screen)
+                .collect();
+        let pixels: Vec<(usize, usize)> = rows.iter().map(|row| row.pixel).collect();
+        assert_eq!(
+            pixels,
+            vec![(0, 0), (1, 0), (2, 0), (0, 1), (1, 1), (2, 1), (0, 0)]
+        );
+        let lit: Vec<bool> = rows.iter().map(|row| row.lit).collect();
+        assert_eq!(lit, vec![false, true, false, false, false, false, false]);
+    }
+
+    #[test]
+    fn test_sample_strengths() {
+        let rows = sample_rows();
+        assert_eq!(rows.len(), 240);
+        // straight from the puzzle description
+        let strengths: Vec<isize> = rows
+            .iter()
+            .filter(|row| Samples::PUZZLE.contains(row.cycle))
+            .map(|row| row.signal_strength())
+            .collect();
+        assert_eq!(strengths, vec![420, 1140, 1800, 2940, 2880, 3960]);
+        assert_eq!(signal_strength_sum(rows.clone(), &Samples::PUZZLE), 13140);
+        assert_eq!(
+            signal_strength_sum(rows, &Samples::parse("60, 220").unwrap()),
+            1140 + 3960
+        );
+    }
+
+    #[test]
+    fn test_wrapped_strengths() {
+        // x is isize::MAX from cycle 3 on, so 20 times it wraps round to -20
+        let program = format!("addx {}\n{}", isize::MAX - 1, "noop\n".repeat(20));
+        let rows: Vec<Row> = Trace::new(&mut CPU::init(&program)).collect();
+        assert_eq!(rows[19].signal_strength(), -20);
+        assert_eq!(signal_strength_sum(rows, &Samples::At(vec![20, 22])), -42);
+        let program = format!("addx {}\n{}", isize::MAX - 1, "noop\n".repeat(218));
+        assert!(CPU::init(&program)
+            .get_cumulative_signal_stregnths()
+            .is_ok());
+    }
+
+    #[test]
+    fn test_samples_parse() {
+        assert_eq!(Samples::parse("20+40"), Some(Samples::PUZZLE));
+        assert_eq!(Samples::parse("1,2"), Some(Samples::At(vec![1, 2])));
+        assert_eq!(Samples::parse("5+0"), None);
+        assert_eq!(Samples::parse("five"), None);
+    }
+
+    #[test]
+    fn test_first_row_of_the_crt() {
+        let lit: String = sample_rows()[..40]
+            .iter()
+            .map(|row| if row.lit { '#' } else { '.' })
+            .collect();
+        assert_eq!(lit, "##..##..##..##..##..##..##..##..##..##..");
+    }
+
+    #[test]
+    fn test_csv_and_json() {
+        let rows: Vec<Row> = Trace::new(&mut CPU::init("addx 3")).collect();
+        assert_eq!(
+            to_csv(rows.clone()),
+            "cycle,ip,instruction,x_during,x_after,signal_strength,pixel_x,pixel_y,lit
+1,0,addx 3,1,1,1,0,0,true
+2,0,addx 3,1,4,2,1,0,true"
+        );
+        assert_eq!(
+            to_json(rows),
+            r#"[
+  {"cycle": 1, "ip": 0, "instruction": "addx 3", "x_during": 1, "x_after": 1, "signal_strength": 1, "pixel": [0, 0], "lit": true},
+  {"cycle": 2, "ip": 0, "instruction": "addx 3", "x_during": 1, "x_after": 4, "signal_strength": 2, "pixel": [1, 0], "lit": true}
+]"#
+        );
+        assert_eq!(to_json(Vec::new()), "[]");
+    }
+}